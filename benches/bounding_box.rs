@@ -1,30 +1,33 @@
-#![feature(test)]
-extern crate test;
-
-use glium::uniform;
+use criterion::{criterion_group, criterion_main, Criterion};
+use glium::{Surface, uniform};
 use mandelwow_lib::Cube;
 use mandelwow_lib::bounding_box::*;
+use mandelwow_lib::render_target::RenderTarget;
 use std::rc::Rc;
 
-#[bench]
-fn bench_bounding_box(b: &mut test::Bencher) {
-    let event_loop = glutin::event_loop::EventLoop::new();
-    let window = glutin::window::WindowBuilder::new();
-    let context = glutin::ContextBuilder::new();
-    let display = glium::Display::new(window, context, &event_loop).unwrap();
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+fn bench_bounding_box(c: &mut Criterion) {
+    let display = common::Headless::new((640, 360)).unwrap();
+    let target = RenderTarget::new(&display, 640, 360);
+    let mut frame = target.framebuffer(&display);
 
     let program = Rc::new(solid_fill_program(&display));
     let bounds = Cube { xmin: -2., xmax: 0.7, ymin: -1., ymax:  1., zmin: -1.1, zmax:  1.1 };
     let bbox = BoundingBox::new(&display, &bounds, program);
-    let mut frame = display.draw();
-    b.iter(|| {
+    c.bench_function("bounding_box", |b| b.iter(|| {
         let mat = [[1., 0., 0., 0.], [0., 1., 0., 0.], [0., 0., 1., 0.], [0., 0., 0., 1.0f32]];
         let uniforms = uniform! {
             model: mat,
             view: mat,
             perspective: mat,
         };
+        frame.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
         bbox.draw(&mut frame, &uniforms);
-    });
-    frame.finish().unwrap();
+        display.finish();
+    }));
 }
+
+criterion_group!(benches, bench_bounding_box);
+criterion_main!(benches);
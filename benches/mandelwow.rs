@@ -0,0 +1,41 @@
+use cgmath::conv::array4x4;
+use cgmath::{Matrix4, Vector3};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use glium::Surface;
+use mandelwow_lib::Cube;
+use mandelwow_lib::mandelwow;
+use mandelwow_lib::options::Quality;
+use mandelwow_lib::render_target::RenderTarget;
+use mandelwow_lib::support::camera::CameraState;
+use mandelwow_lib::support::vec3::Vec3;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+fn bench_mandelwow(c: &mut Criterion) {
+    let display = common::Headless::new((640, 360)).unwrap();
+    let target = RenderTarget::new(&display, 640, 360);
+    let mut frame = target.framebuffer(&display);
+
+    let program = mandelwow::program(&display);
+    let bounds = Cube { xmin: -2., xmax: 0.7, ymin: -1., ymax:  1., zmin: -1.1, zmax:  1.1 };
+    let model = array4x4(Matrix4::from_translation(Vector3::new(0.0, 0.0, -3.0)));
+    let mut camera = CameraState::new();
+    camera.set_aspect(640.0 / 360.0);
+    camera.look_at(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, -3.0));
+
+    let mut group = c.benchmark_group("mandelwow");
+    for quality in [Quality::Low, Quality::Medium, Quality::High] {
+        let slices = quality.slices();
+        group.bench_function(BenchmarkId::from_parameter(slices), |b| b.iter(|| {
+            frame.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+            mandelwow::draw(&display, &mut frame, &program, model, camera.get_view(),
+                            camera.get_perspective(), &bounds, 0.3, slices);
+            display.finish();
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_mandelwow);
+criterion_main!(benches);
@@ -1,28 +1,34 @@
-#![feature(test)]
-extern crate test;
-
-use glium::uniform;
+use criterion::{criterion_group, criterion_main, Criterion};
+use glium::{Surface, uniform};
+use mandelwow_lib::lights::Lights;
+use mandelwow_lib::render_target::RenderTarget;
 use mandelwow_lib::shaded_cube::*;
-use std::rc::Rc;
 
-#[bench]
-fn bench_shaded_cube(b: &mut test::Bencher) {
-    let event_loop = glutin::event_loop::EventLoop::new();
-    let window = glutin::window::WindowBuilder::new();
-    let context = glutin::ContextBuilder::new();
-    let display = glium::Display::new(window, context, &event_loop).unwrap();
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+fn bench_shaded_cube(c: &mut Criterion) {
+    let display = common::Headless::new((640, 360)).unwrap();
+    let target = RenderTarget::new(&display, 640, 360);
+    let mut frame = target.framebuffer(&display);
 
-    let program = Rc::new(shaded_program(&display));
-    let cube = ShadedCube::new(&display, program);
-    let mut frame = display.draw();
-    b.iter(|| {
+    let program = shaded_program(&display);
+    let cube = ShadedCube::new(&display);
+    let lights = Lights::default();
+    c.bench_function("shaded_cube", |b| b.iter(|| {
         let model =     [[0.7, 0.5, -0.5, 0.0], [0.0, 0.7, 0.7, 0.0], [0.7, -0.5,  0.5,  0.0], [0., 0., -3.0, 1.0f32]];
         let perspview = [[0.5, 0.0,  0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0,  0.0, -1.0, -1.0], [0., 0., -0.2, 0.0f32]];
         let uniforms = uniform! {
             model: model,
             perspview: perspview,
+            col: [0.5, 0.5, 0.5f32],
+            eye: [0.0, 0.0, 0.0f32],
         };
-        cube.draw(&mut frame, &uniforms);
-    });
-    frame.finish().unwrap();
+        frame.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+        cube.draw(&mut frame, &program, uniforms, &lights, None);
+        display.finish();
+    }));
 }
+
+criterion_group!(benches, bench_shaded_cube);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+use cgmath::conv::array4x4;
+use cgmath::{Matrix4, Vector3};
+use criterion::{criterion_group, criterion_main, Criterion};
+use glium::Surface;
+use mandelwow_lib::render_target::RenderTarget;
+use mandelwow_lib::text::Text;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+fn bench_text(c: &mut Criterion) {
+    let display = common::Headless::new((640, 360)).unwrap();
+    let target = RenderTarget::new(&display, 640, 360);
+    let mut frame = target.framebuffer(&display);
+
+    let text = Text::new(&display);
+    let perspview = array4x4(cgmath::perspective(cgmath::Deg(90.0f32), 640.0 / 360.0, 0.1, 100.0));
+    c.bench_function("text", |b| b.iter(|| {
+        frame.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+        // A line of glyphs, like a row of the sea.
+        for (i, c) in "MANDELWOW".chars().enumerate() {
+            let model = Matrix4::from_translation(Vector3::new(i as f32 - 4.0, 0.0, -5.0));
+            text.draw(&mut frame, c, &model, &perspview);
+        }
+        display.finish();
+    }));
+}
+
+criterion_group!(benches, bench_text);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mandelwow_lib::options::{Options, Quality};
+use mandelwow_lib::render_target::RenderTarget;
+use mandelwow_lib::support::camera::CameraState;
+use mandelwow_lib::support::vec3::Vec3;
+use mandelwow_lib::world::{World, MODEL_Z};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 360;
+
+fn bench_draw_frame(c: &mut Criterion) {
+    let display = common::Headless::new((WIDTH, HEIGHT)).unwrap();
+    // Stands in for the window.
+    let target = RenderTarget::new(&display, WIDTH, HEIGHT);
+    let mut frame = target.framebuffer(&display);
+
+    let mut camera = CameraState::new();
+    camera.set_aspect(WIDTH as f32 / HEIGHT as f32);
+    camera.look_at(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, MODEL_Z));
+
+    let mut group = c.benchmark_group("draw_frame");
+    for quality in [Quality::Low, Quality::Medium, Quality::High] {
+        let options = Options { quality, ..Default::default() };
+        let world = World::new(&display, &options);
+        group.bench_function(BenchmarkId::from_parameter(format!("{:?}", quality)), |b| {
+            let mut t = 0.0;
+            b.iter(|| {
+                world.draw_frame(&display, &mut frame, &camera, t);
+                display.finish();
+                t += 1.0 / 60.0;
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_draw_frame);
+criterion_main!(benches);
@@ -0,0 +1,106 @@
+// Numbered camera bookmarks.
+//
+// Each bookmark remembers the camera position, orientation and field of
+// view, along with the time and the wow factor (the 4th dimension of the
+// fractal) at the moment it was stored. They are saved as text, one per line:
+//
+//   # slot  pos.x pos.y pos.z  q.w q.x q.y q.z  fov  t  wow
+//   1  0 0 0  1 0 0 0  1.5708  12.5  0.42
+
+use crate::support::vec3::Vec3;
+use cgmath::Quaternion;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const SLOTS: u8 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bookmark {
+    pub pos: Vec3,
+    pub orientation: Quaternion<f32>,
+    /// Vertical field of view, in radians.
+    pub fov: f32,
+    pub t: f32,
+    pub wow: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bookmarks {
+    slots: BTreeMap<u8, Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn new() -> Bookmarks {
+        Bookmarks::default()
+    }
+
+    pub fn get(&self, slot: u8) -> Option<&Bookmark> {
+        self.slots.get(&slot)
+    }
+
+    pub fn set(&mut self, slot: u8, bookmark: Bookmark) {
+        assert!(slot < SLOTS, "bookmark slot out of range: {}", slot);
+        self.slots.insert(slot, bookmark);
+    }
+
+    pub fn remove(&mut self, slot: u8) {
+        self.slots.remove(&slot);
+    }
+
+    pub fn parse(text: &str) -> Result<Bookmarks, String> {
+        let mut bookmarks = Bookmarks::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut columns = line.split_whitespace();
+            let slot: u8 = match columns.next().map(str::parse) {
+                Some(Ok(slot)) if slot < SLOTS => slot,
+                _ => return Err(format!("line {}: invalid bookmark slot", i + 1)),
+            };
+            let v: Vec<f32> = columns
+                .map(|c| c.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|err| format!("line {}: {}", i + 1, err))?;
+            if v.len() != 10 {
+                return Err(format!("line {}: expected 11 numbers, got {}", i + 1, v.len() + 1));
+            }
+            bookmarks.set(slot, Bookmark {
+                pos: Vec3(v[0], v[1], v[2]),
+                orientation: Quaternion::new(v[3], v[4], v[5], v[6]),
+                fov: v[7],
+                t: v[8],
+                wow: v[9],
+            });
+        }
+        Ok(bookmarks)
+    }
+
+    pub fn load(filename: &Path) -> io::Result<Bookmarks> {
+        let text = fs::read_to_string(filename)?;
+        Bookmarks::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save(&self, filename: &Path) -> io::Result<()> {
+        if let Some(dir) = filename.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(filename, self.to_string())
+    }
+}
+
+impl fmt::Display for Bookmarks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# slot  pos.x pos.y pos.z  q.w q.x q.y q.z  fov  t  wow")?;
+        for (slot, b) in &self.slots {
+            let q = b.orientation;
+            writeln!(f, "{}  {} {} {}  {} {} {} {}  {}  {}  {}",
+                     slot, b.pos.0, b.pos.1, b.pos.2, q.s, q.v.x, q.v.y, q.v.z, b.fov, b.t, b.wow)?;
+        }
+        Ok(())
+    }
+}
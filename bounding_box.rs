@@ -1,10 +1,11 @@
 use crate::cube::Cube;
 use glium;
-use glium::{Display, Program, Surface, implement_vertex};
+use glium::backend::Facade;
+use glium::{Program, Surface, implement_vertex};
 use glium::index::{IndexBuffer, PrimitiveType};
 use std::rc::Rc;
 
-pub fn solid_fill_program(display: &Display) -> Program {
+pub fn solid_fill_program<F: ?Sized + Facade>(display: &F) -> Program {
     let vertex_shader_src = include_str!("shaders/solid.vert");
     let fragment_shader_src = include_str!("shaders/solid.frag");
     Program::from_source(display, vertex_shader_src, fragment_shader_src, None).unwrap()
@@ -21,7 +22,7 @@ pub struct BoundingBox {
 }
 
 impl BoundingBox {
-    pub fn new(display: &Display, c: &Cube, program: Rc<Program>) -> BoundingBox {
+    pub fn new<F: ?Sized + Facade>(display: &F, c: &Cube, program: Rc<Program>) -> BoundingBox {
         let vertex_data = [
             Vertex { position: [c.xmin, c.ymin, c.zmin] },
             Vertex { position: [c.xmax, c.ymin, c.zmin] },
@@ -44,8 +45,8 @@ impl BoundingBox {
         }
     }
 
-    pub fn draw<U>(&self, frame: &mut glium::Frame,
-                   uniforms: &U) where U: glium::uniforms::Uniforms {
+    pub fn draw<S, U>(&self, frame: &mut S,
+                      uniforms: &U) where S: Surface, U: glium::uniforms::Uniforms {
         let params = glium::DrawParameters {
             depth: glium::Depth {
                 test: glium::draw_parameters::DepthTest::IfLess,
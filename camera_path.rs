@@ -0,0 +1,162 @@
+// Scripted camera paths.
+//
+// A path is a list of keyframes (time, position, target, field of view),
+// interpolated with Catmull-Rom splines and sampled with the timeline time.
+// Paths are stored as text, one keyframe per line:
+//
+//   # t  pos.x pos.y pos.z  target.x target.y target.z  fov
+//   0.0  0 0 0  0 0 -3  1.5708
+
+use crate::support::vec3::Vec3;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    pub t: f32,
+    pub pos: Vec3,
+    pub target: Vec3,
+    /// Vertical field of view, in radians.
+    pub fov: f32,
+}
+
+impl Keyframe {
+    fn to_array(self) -> [f32; 7] {
+        [
+            self.pos.0, self.pos.1, self.pos.2,
+            self.target.0, self.target.1, self.target.2,
+            self.fov,
+        ]
+    }
+
+    fn from_array(t: f32, v: [f32; 7]) -> Keyframe {
+        Keyframe {
+            t,
+            pos: Vec3(v[0], v[1], v[2]),
+            target: Vec3(v[3], v[4], v[5]),
+            fov: v[6],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CameraPath {
+    keys: Vec<Keyframe>,  // Sorted by time.
+}
+
+// Cubic Hermite interpolation between p1 and p2 with tangents m1 and m2, u in 0..1.
+fn hermite(p1: f32, p2: f32, m1: f32, m2: f32, u: f32) -> f32 {
+    let u2 = u * u;
+    let u3 = u2 * u;
+    (2. * u3 - 3. * u2 + 1.) * p1
+        + (u3 - 2. * u2 + u) * m1
+        + (-2. * u3 + 3. * u2) * p2
+        + (u3 - u2) * m2
+}
+
+impl CameraPath {
+    pub fn new() -> CameraPath {
+        CameraPath::default()
+    }
+
+    pub fn keys(&self) -> &[Keyframe] {
+        &self.keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Insert a keyframe, replacing any existing one at the same time.
+    /// Keyframes at a non-finite time are ignored.
+    pub fn add(&mut self, key: Keyframe) {
+        if !key.t.is_finite() {
+            return;
+        }
+        match self.keys.binary_search_by(|k| k.t.total_cmp(&key.t)) {
+            Ok(i) => self.keys[i] = key,
+            Err(i) => self.keys.insert(i, key),
+        }
+    }
+
+    /// Camera pose at time t. Before the first and after the last keyframe
+    /// the camera holds still. There is no pose at a NaN time.
+    pub fn sample(&self, t: f32) -> Option<Keyframe> {
+        if t.is_nan() {
+            return None;
+        }
+        let keys = &self.keys;
+        let first = keys.first()?;
+        let last = keys.last()?;
+        if t <= first.t {
+            return Some(Keyframe { t, ..*first });
+        }
+        if t >= last.t {
+            return Some(Keyframe { t, ..*last });
+        }
+
+        // Segment k1..k2 containing t, with neighbours k0 and k3 (clamped at the ends).
+        let i2 = keys.iter().position(|k| k.t > t).unwrap();
+        let i1 = i2 - 1;
+        let i0 = i1.saturating_sub(1);
+        let i3 = (i2 + 1).min(keys.len() - 1);
+        let (k0, k1, k2, k3) = (&keys[i0], &keys[i1], &keys[i2], &keys[i3]);
+        let (p0, p1, p2, p3) = (k0.to_array(), k1.to_array(), k2.to_array(), k3.to_array());
+
+        // Catmull-Rom tangents, scaled for uneven keyframe spacing.
+        let dt = k2.t - k1.t;
+        let u = (t - k1.t) / dt;
+        let mut v = [0f32; 7];
+        for (c, out) in v.iter_mut().enumerate() {
+            let m1 = if i0 == i1 { p2[c] - p1[c] } else { (p2[c] - p0[c]) / (k2.t - k0.t) * dt };
+            let m2 = if i3 == i2 { p2[c] - p1[c] } else { (p3[c] - p1[c]) / (k3.t - k1.t) * dt };
+            *out = hermite(p1[c], p2[c], m1, m2, u);
+        }
+        Some(Keyframe::from_array(t, v))
+    }
+
+    pub fn parse(text: &str) -> Result<CameraPath, String> {
+        let mut path = CameraPath::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let v: Vec<f32> = line
+                .split_whitespace()
+                .map(|c| c.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|err| format!("line {}: {}", i + 1, err))?;
+            if v.len() != 8 {
+                return Err(format!("line {}: expected 8 numbers, got {}", i + 1, v.len()));
+            }
+            if !v.iter().all(|c| c.is_finite()) {
+                return Err(format!("line {}: numbers must be finite", i + 1));
+            }
+            path.add(Keyframe::from_array(v[0], [v[1], v[2], v[3], v[4], v[5], v[6], v[7]]));
+        }
+        Ok(path)
+    }
+
+    pub fn load(filename: &Path) -> io::Result<CameraPath> {
+        let text = fs::read_to_string(filename)?;
+        CameraPath::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save(&self, filename: &Path) -> io::Result<()> {
+        fs::write(filename, self.to_string())
+    }
+}
+
+impl fmt::Display for CameraPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# t  pos.x pos.y pos.z  target.x target.y target.z  fov")?;
+        for k in &self.keys {
+            writeln!(f, "{}  {} {} {}  {} {} {}  {}",
+                     k.t, k.pos.0, k.pos.1, k.pos.2, k.target.0, k.target.1, k.target.2, k.fov)?;
+        }
+        Ok(())
+    }
+}
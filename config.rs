@@ -0,0 +1,197 @@
+// Persistent user settings, stored as a simple INI-style file:
+//
+//   # comment
+//   [display]
+//   size = 1920x1080
+//   vsync = off
+//
+// The file lives in $XDG_CONFIG_HOME/mandelwow/mandelwow.conf and is
+// rewritten on exit, so comments and ordering are not preserved.
+
+use crate::support::vec3::Vec3;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    path: Option<PathBuf>,
+    sections: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Config {
+    /// $XDG_CONFIG_HOME/mandelwow/mandelwow.conf, falling back to ~/.config
+    /// (or %APPDATA% on Windows).
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+        Some(dir.join("mandelwow").join("mandelwow.conf"))
+    }
+
+    /// Load the user configuration from the default path.
+    pub fn load() -> Config {
+        match Config::default_path() {
+            Some(path) => Config::load_or_empty(path),
+            None => Config::default(),
+        }
+    }
+
+    /// A missing file yields an empty configuration which will be saved to
+    /// `path`. A broken one yields an empty configuration which won't be
+    /// saved, so the user doesn't lose the settings in it.
+    pub fn load_or_empty(path: PathBuf) -> Config {
+        match Config::load_from(&path) {
+            Ok(config) => config,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Config { path: Some(path), ..Default::default() },
+            Err(err) => {
+                println!("Couldn't load config {}: {}. It won't be saved.", path.display(), err);
+                Config::default()
+            }
+        }
+    }
+
+    pub fn load_from(path: &Path) -> io::Result<Config> {
+        let text = fs::read_to_string(path)?;
+        let mut config = Config::parse(&text)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        config.path = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    pub fn parse(text: &str) -> Result<Config, ParseError> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') {
+                if !line.ends_with(']') {
+                    return Err(ParseError { line: i + 1, msg: "unterminated section".to_string() });
+                }
+                section = line[1..line.len() - 1].trim().to_string();
+                continue;
+            }
+            match line.find('=') {
+                Some(eq) => {
+                    let key = line[..eq].trim();
+                    if key.is_empty() {
+                        return Err(ParseError { line: i + 1, msg: "missing key".to_string() });
+                    }
+                    config.set(&section, key, line[eq + 1..].trim());
+                }
+                None => return Err(ParseError { line: i + 1, msg: "expected key = value".to_string() }),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.sections.get(section)?.get(key).map(|v| v.as_str())
+    }
+
+    pub fn set<T: ToString>(&mut self, section: &str, key: &str, value: T) {
+        self.sections
+            .entry(section.to_string())
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    pub fn remove(&mut self, section: &str, key: &str) {
+        if let Some(keys) = self.sections.get_mut(section) {
+            keys.remove(key);
+        }
+    }
+
+    /// All the key/value pairs of a section, sorted by key.
+    pub fn section(&self, section: &str) -> Vec<(&str, &str)> {
+        match self.sections.get(section) {
+            Some(keys) => keys.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The names of all the sections, sorted.
+    pub fn section_names(&self) -> Vec<&str> {
+        self.sections.keys().map(|name| name.as_str()).collect()
+    }
+
+    /// A whitespace-separated list of numbers.
+    pub fn get_floats(&self, section: &str, key: &str) -> Option<Vec<f32>> {
+        self.get(section, key)?
+            .split_whitespace()
+            .map(|c| c.parse().ok())
+            .collect()
+    }
+
+    pub fn set_floats(&mut self, section: &str, key: &str, v: &[f32]) {
+        let v: Vec<String> = v.iter().map(|c| c.to_string()).collect();
+        self.set(section, key, v.join(" "));
+    }
+
+    pub fn get_vec3(&self, section: &str, key: &str) -> Option<Vec3> {
+        let v = self.get_floats(section, key)?;
+        match v.as_slice() {
+            &[x, y, z] => Some(Vec3(x, y, z)),
+            _ => None,
+        }
+    }
+
+    pub fn set_vec3(&mut self, section: &str, key: &str, v: Vec3) {
+        self.set_floats(section, key, &[v.0, v.1, v.2]);
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# MandelWow configuration, rewritten on exit.")?;
+        for (name, keys) in &self.sections {
+            if keys.is_empty() {
+                continue;
+            }
+            writeln!(f)?;
+            if !name.is_empty() {
+                writeln!(f, "[{}]", name)?;
+            }
+            for (key, value) in keys {
+                writeln!(f, "{} = {}", key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+// Analog camera controls from a game controller, via SDL2.
+//
+// Left stick moves, right stick looks around, the triggers move down/up.
+// Settings come from the [gamepad] section of the config file:
+//
+//   [gamepad]
+//   deadzone = 0.15
+//   move_sensitivity = 1.0
+//   look_sensitivity = 1.0
+//   invert_y = false
+
+use crate::config::Config;
+use crate::options::parse_bool;
+use sdl2::controller::{Axis, GameController};
+use sdl2::event::Event;
+use sdl2::{EventPump, GameControllerSubsystem, Sdl};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GamepadSettings {
+    /// Fraction of the stick travel ignored around the center.
+    pub deadzone: f32,
+    pub move_sensitivity: f32,
+    pub look_sensitivity: f32,
+    pub invert_y: bool,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> GamepadSettings {
+        GamepadSettings {
+            deadzone: 0.15,
+            move_sensitivity: 1.0,
+            look_sensitivity: 1.0,
+            invert_y: false,
+        }
+    }
+}
+
+impl GamepadSettings {
+    pub fn from_config(config: &Config) -> Result<GamepadSettings, String> {
+        let mut settings = GamepadSettings::default();
+        for (key, v) in config.section("gamepad") {
+            let invalid = || format!("invalid value for gamepad.{} in config: {}", key, v);
+            let sensitivity = || v.parse().ok().filter(|&s: &f32| s.is_finite() && s > 0.0).ok_or_else(invalid);
+            match key {
+                "deadzone" => {
+                    settings.deadzone = v.parse().map_err(|_| invalid())?;
+                    if !(0.0..1.0).contains(&settings.deadzone) {
+                        return Err(invalid());
+                    }
+                }
+                "move_sensitivity" => settings.move_sensitivity = sensitivity()?,
+                "look_sensitivity" => settings.look_sensitivity = sensitivity()?,
+                "invert_y" => settings.invert_y = parse_bool(v).ok_or_else(invalid)?,
+                _ => (),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// Analog input in the -1..1 range, deadzone and sensitivity already applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GamepadState {
+    pub strafe: f32,
+    pub forward: f32,
+    pub up: f32,
+    pub look_x: f32,
+    pub look_y: f32,
+}
+
+/// Radial deadzone: rescale the stick vector so motion starts smoothly at the edge.
+pub fn apply_deadzone(x: f32, y: f32, deadzone: f32) -> (f32, f32) {
+    let len = (x * x + y * y).sqrt();
+    if len <= deadzone {
+        return (0.0, 0.0);
+    }
+    let scale = ((len - deadzone) / (1.0 - deadzone)).min(1.0) / len;
+    (x * scale, y * scale)
+}
+
+fn axis_value(controller: &GameController, axis: Axis) -> f32 {
+    (controller.axis(axis) as f32 / 32767.0).max(-1.0)
+}
+
+pub struct Gamepad {
+    subsystem: GameControllerSubsystem,
+    event_pump: EventPump,
+    controller: Option<GameController>,
+    pub settings: GamepadSettings,
+}
+
+impl Gamepad {
+    pub fn new(sdl: &Sdl, settings: GamepadSettings) -> Result<Gamepad, String> {
+        let mut gamepad = Gamepad {
+            subsystem: sdl.game_controller()?,
+            event_pump: sdl.event_pump()?,
+            controller: None,
+            settings,
+        };
+        for i in 0..gamepad.subsystem.num_joysticks()? {
+            gamepad.open(i);
+            if gamepad.controller.is_some() {
+                break;
+            }
+        }
+        Ok(gamepad)
+    }
+
+    fn open(&mut self, joystick_index: u32) {
+        if !self.subsystem.is_game_controller(joystick_index) {
+            return;
+        }
+        match self.subsystem.open(joystick_index) {
+            Ok(controller) => {
+                println!("Using game controller: {}", controller.name());
+                self.controller = Some(controller);
+            }
+            Err(err) => println!("Couldn't open game controller {}: {}", joystick_index, err),
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.controller.is_some()
+    }
+
+    /// Handle hot-plugging and sample the current stick positions.
+    pub fn poll(&mut self) -> GamepadState {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        for event in events {
+            match event {
+                Event::ControllerDeviceAdded { which, .. } if self.controller.is_none() => {
+                    self.open(which);
+                }
+                Event::ControllerDeviceRemoved { which, .. }
+                        if self.controller.as_ref().map(|c| c.instance_id()) == Some(which) => {
+                    println!("Game controller disconnected");
+                    self.controller = None;
+                }
+                _ => (),
+            }
+        }
+
+        let controller = match self.controller {
+            Some(ref controller) => controller,
+            None => return GamepadState::default(),
+        };
+        let s = &self.settings;
+        let (strafe, forward) = apply_deadzone(
+            axis_value(controller, Axis::LeftX),
+            -axis_value(controller, Axis::LeftY),
+            s.deadzone,
+        );
+        let (look_x, look_y) = apply_deadzone(
+            axis_value(controller, Axis::RightX),
+            axis_value(controller, Axis::RightY),
+            s.deadzone,
+        );
+        let up = axis_value(controller, Axis::TriggerRight) - axis_value(controller, Axis::TriggerLeft);
+        GamepadState {
+            strafe: strafe * s.move_sensitivity,
+            forward: forward * s.move_sensitivity,
+            up: if up.abs() > s.deadzone { up * s.move_sensitivity } else { 0.0 },
+            look_x: look_x * s.look_sensitivity,
+            look_y: (if s.invert_y { -look_y } else { look_y }) * s.look_sensitivity,
+        }
+    }
+}
@@ -0,0 +1,271 @@
+// Maps keys and mouse buttons to actions.
+//
+// The default bindings can be overridden in the [keys] section of the
+// configuration file, one action per line, e.g.:
+//
+//   [keys]
+//   quit = Escape, Q
+//   screenshot = F12
+//   move_forward = W, Mouse:Left
+
+use crate::config::Config;
+use glium::glutin::event::{ElementState, MouseButton, VirtualKeyCode, WindowEvent};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    ToggleBoundingBox,
+    TogglePause,
+    SeekForward,
+    SeekBackward,
+    Screenshot,
+    ToggleFullscreen,
+    ToggleLetterbox,
+    ToggleRetro,
+
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    MoveForward,
+    MoveBackward,
+    TurnLeft,
+    TurnRight,
+    TurnUp,
+    TurnDown,
+    RollLeft,
+    RollRight,
+    ToggleMouseLook,
+    ToggleOrbit,
+    RecordKeyframe,
+    TogglePathPlayback,
+    ZoomIn,
+    ZoomOut,
+    DollyZoomIn,
+    DollyZoomOut,
+    ToggleProjection,
+    /// Recall a camera bookmark, or store it while StoreBookmark is held.
+    Bookmark(u8),
+    StoreBookmark,
+}
+
+macro_rules! actions {
+    ($($action:ident $(($arg:expr))? => $name:expr),* $(,)?) => {
+        const ACTIONS: &[(Action, &str)] = &[$((Action::$action $(($arg))?, $name)),*];
+    };
+}
+
+actions! {
+    Quit => "quit",
+    ToggleBoundingBox => "toggle_bounding_box",
+    TogglePause => "toggle_pause",
+    SeekForward => "seek_forward",
+    SeekBackward => "seek_backward",
+    Screenshot => "screenshot",
+    ToggleFullscreen => "toggle_fullscreen",
+    ToggleLetterbox => "toggle_letterbox",
+    ToggleRetro => "toggle_retro",
+    MoveLeft => "move_left",
+    MoveRight => "move_right",
+    MoveUp => "move_up",
+    MoveDown => "move_down",
+    MoveForward => "move_forward",
+    MoveBackward => "move_backward",
+    TurnLeft => "turn_left",
+    TurnRight => "turn_right",
+    TurnUp => "turn_up",
+    TurnDown => "turn_down",
+    RollLeft => "roll_left",
+    RollRight => "roll_right",
+    ToggleMouseLook => "toggle_mouse_look",
+    ToggleOrbit => "toggle_orbit",
+    RecordKeyframe => "record_keyframe",
+    TogglePathPlayback => "toggle_path_playback",
+    ZoomIn => "zoom_in",
+    ZoomOut => "zoom_out",
+    DollyZoomIn => "dolly_zoom_in",
+    DollyZoomOut => "dolly_zoom_out",
+    ToggleProjection => "toggle_projection",
+    Bookmark(0) => "bookmark_0",
+    Bookmark(1) => "bookmark_1",
+    Bookmark(2) => "bookmark_2",
+    Bookmark(3) => "bookmark_3",
+    Bookmark(4) => "bookmark_4",
+    Bookmark(5) => "bookmark_5",
+    Bookmark(6) => "bookmark_6",
+    Bookmark(7) => "bookmark_7",
+    Bookmark(8) => "bookmark_8",
+    Bookmark(9) => "bookmark_9",
+    StoreBookmark => "store_bookmark",
+}
+
+impl Action {
+    /// The name used in the configuration file.
+    pub fn name(self) -> &'static str {
+        ACTIONS.iter().find(|(a, _)| *a == self).unwrap().1
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|(_, n)| *n == name).map(|(a, _)| *a)
+    }
+
+    pub fn all() -> impl Iterator<Item = Action> {
+        ACTIONS.iter().map(|(a, _)| *a)
+    }
+}
+
+macro_rules! keys {
+    ($($key:ident),* $(,)?) => {
+        const KEYS: &[(VirtualKeyCode, &str)] = &[$((VirtualKeyCode::$key, stringify!($key))),*];
+    };
+}
+
+// Keys which can be named in the configuration file.
+keys! {
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadEnter,
+    Escape, Return, Space, Tab, Back, Insert, Delete, Home, End, PageUp, PageDown,
+    Left, Right, Up, Down,
+    LShift, RShift, LControl, RControl, LAlt, RAlt,
+    Minus, Equals, Comma, Period, Slash, Backslash, Semicolon, Apostrophe, Grave,
+    LBracket, RBracket,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    pub fn parse(s: &str) -> Option<Binding> {
+        if let Some(button) = s.strip_prefix("Mouse:") {
+            return match button {
+                "Left" => Some(Binding::Mouse(MouseButton::Left)),
+                "Right" => Some(Binding::Mouse(MouseButton::Right)),
+                "Middle" => Some(Binding::Mouse(MouseButton::Middle)),
+                n => n.parse().ok().map(|n| Binding::Mouse(MouseButton::Other(n))),
+            };
+        }
+        KEYS.iter().find(|(_, name)| *name == s).map(|(key, _)| Binding::Key(*key))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Mouse(MouseButton::Other(n)) => write!(f, "Mouse:{}", n),
+            Binding::Mouse(button) => write!(f, "Mouse:{:?}", button),
+        }
+    }
+}
+
+pub struct Keymap {
+    bindings: Vec<(Binding, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        use self::Action::*;
+        use self::VirtualKeyCode as K;
+        let keys = [
+            (K::Escape, Quit),
+            (K::Q, Quit),
+            (K::B, ToggleBoundingBox),
+            (K::P, TogglePause),
+            (K::PageUp, SeekForward),
+            (K::PageDown, SeekBackward),
+            (K::F10, Screenshot),
+            (K::F11, ToggleFullscreen),
+            (K::Return, ToggleFullscreen),
+            (K::F9, ToggleLetterbox),
+            (K::F8, ToggleRetro),
+            (K::Left, MoveLeft),
+            (K::Right, MoveRight),
+            (K::Up, MoveUp),
+            (K::Down, MoveDown),
+            (K::W, MoveForward),
+            (K::S, MoveBackward),
+            (K::A, TurnLeft),
+            (K::D, TurnRight),
+            (K::R, TurnUp),
+            (K::F, TurnDown),
+            (K::Z, RollLeft),
+            (K::C, RollRight),
+            (K::G, ToggleMouseLook),
+            (K::O, ToggleOrbit),
+            (K::K, RecordKeyframe),
+            (K::L, TogglePathPlayback),
+            (K::Equals, ZoomIn),
+            (K::NumpadAdd, ZoomIn),
+            (K::Minus, ZoomOut),
+            (K::NumpadSubtract, ZoomOut),
+            (K::Period, DollyZoomIn),
+            (K::Comma, DollyZoomOut),
+            (K::V, ToggleProjection),
+            (K::Key0, Bookmark(0)),
+            (K::Key1, Bookmark(1)),
+            (K::Key2, Bookmark(2)),
+            (K::Key3, Bookmark(3)),
+            (K::Key4, Bookmark(4)),
+            (K::Key5, Bookmark(5)),
+            (K::Key6, Bookmark(6)),
+            (K::Key7, Bookmark(7)),
+            (K::Key8, Bookmark(8)),
+            (K::Key9, Bookmark(9)),
+            (K::LShift, StoreBookmark),
+            (K::RShift, StoreBookmark),
+        ];
+        Keymap {
+            bindings: keys.iter().map(|&(key, action)| (Binding::Key(key), action)).collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// The default keymap, with the overrides from the [keys] section of the config.
+    pub fn from_config(config: &Config) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+        for (name, value) in config.section("keys") {
+            let action = Action::from_name(name)
+                .ok_or_else(|| format!("unknown action in config: {}", name))?;
+            let bindings = value
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| Binding::parse(s).ok_or_else(|| format!("unknown key for {}: {}", name, s)))
+                .collect::<Result<Vec<_>, _>>()?;
+            keymap.bind(action, &bindings);
+        }
+        Ok(keymap)
+    }
+
+    /// Replace all the bindings of an action.
+    pub fn bind(&mut self, action: Action, bindings: &[Binding]) {
+        self.bindings.retain(|&(b, a)| a != action && !bindings.contains(&b));
+        self.bindings.extend(bindings.iter().map(|&b| (b, action)));
+    }
+
+    pub fn bindings(&self, action: Action) -> Vec<Binding> {
+        self.bindings.iter().filter(|(_, a)| *a == action).map(|(b, _)| *b).collect()
+    }
+
+    pub fn action(&self, binding: Binding) -> Option<Action> {
+        self.bindings.iter().find(|(b, _)| *b == binding).map(|(_, a)| *a)
+    }
+
+    /// Returns the bound action and whether it was pressed or released.
+    pub fn translate(&self, event: &WindowEvent<'_>) -> Option<(Action, bool)> {
+        let (binding, state) = match *event {
+            WindowEvent::KeyboardInput { input, .. } => (Binding::Key(input.virtual_keycode?), input.state),
+            WindowEvent::MouseInput { button, state, .. } => (Binding::Mouse(button), state),
+            _ => return None,
+        };
+        self.action(binding).map(|action| (action, state == ElementState::Pressed))
+    }
+}
@@ -1,15 +1,42 @@
+pub mod bookmarks;
 pub mod bounding_box;
+pub mod camera_path;
+pub mod config;
 pub mod cube;
+pub mod gamepad;
+pub mod keymap;
+pub mod lights;
 pub mod mandelwow;
+pub mod options;
+pub mod postprocess;
+pub mod render_target;
+pub mod retro;
 pub mod shaded_cube;
+pub mod shadow;
 pub mod screenshot;
+pub mod sea;
 pub mod sound;
 pub mod support;
+pub mod sync_server;
 pub mod text;
 pub mod timer;
+pub mod uniform_array;
+pub mod world;
 
+pub use crate::bookmarks::Bookmarks;
 pub use crate::bounding_box::BoundingBox;
+pub use crate::camera_path::CameraPath;
+pub use crate::config::Config;
 pub use crate::cube::Cube;
+pub use crate::gamepad::{Gamepad, GamepadSettings};
+pub use crate::keymap::Keymap;
+pub use crate::lights::Lights;
+pub use crate::options::Options;
+pub use crate::postprocess::{PostProcess, PostScenes, PostSettings};
+pub use crate::render_target::RenderTarget;
+pub use crate::retro::{RetroFilter, RetroSettings};
 pub use crate::shaded_cube::ShadedCube;
+pub use crate::shadow::ShadowMap;
 pub use crate::text::Text;
 pub use crate::timer::Timer;
+pub use crate::world::World;
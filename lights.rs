@@ -0,0 +1,130 @@
+// Lights for the shaded geometry: an ambient term plus a few directional or
+// point lights, shaded per fragment with Blinn-Phong (shaders/lights.glsl).
+//
+// They can be set in the [lights] section of the config file, replacing the
+// default lights when any lightN is given:
+//
+//   [lights]
+//   ambient = 0.1 0.1 0.1
+//   specular = 0.3      # Strength of the highlights
+//   shininess = 32      # Size of the highlights, larger is smaller
+//   # directional  x y z (towards the light)  r g b
+//   light1 = directional  -0.5 0.7 0.6  0.8 0.8 0.8
+//   # point  x y z  r g b  attenuation
+//   light2 = point  0 2 0  1 1 1  0.05
+//
+// Colors are linear and may exceed 1 for brighter lights. Point lights fade
+// as 1 / (1 + attenuation * distance²). The first directional light casts
+// shadows (see shadow.rs).
+
+use crate::config::Config;
+
+/// Lights beyond this are rejected: the shaders have a fixed size array.
+pub const MAX_LIGHTS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Light {
+    /// Infinitely far away, like the sun.
+    Directional { direction: [f32; 3], color: [f32; 3] },
+    Point { position: [f32; 3], color: [f32; 3], attenuation: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lights {
+    pub ambient: [f32; 3],
+    pub specular: f32,
+    pub shininess: f32,
+    pub lights: Vec<Light>,
+}
+
+impl Default for Lights {
+    fn default() -> Lights {
+        Lights {
+            ambient: [0.1, 0.1, 0.1],
+            specular: 0.3,
+            shininess: 32.0,
+            lights: vec![
+                Light::Directional { direction: [-0.5, 0.7, 0.6], color: [0.8, 0.8, 0.8] },
+                Light::Point { position: [0.0, 2.0, 0.0], color: [1.0, 1.0, 1.0], attenuation: 0.05 },
+            ],
+        }
+    }
+}
+
+/// The lights uniform array of shaders/lights.glsl.
+pub type LightsArray = [[f32; 4]; 2 + 2 * MAX_LIGHTS];
+
+impl Lights {
+    pub fn from_config(config: &Config) -> Result<Lights, String> {
+        let mut settings = Lights::default();
+        let mut lights = Vec::new();
+        for (key, v) in config.section("lights") {
+            let invalid = || format!("invalid value for lights.{} in config: {}", key, v);
+            match key {
+                "ambient" => {
+                    let p = floats(v).filter(|p| p.len() == 3).ok_or_else(invalid)?;
+                    settings.ambient = [p[0], p[1], p[2]];
+                }
+                "specular" => settings.specular = v.parse().map_err(|_| invalid())?,
+                "shininess" => settings.shininess = v.parse().ok().filter(|&s: &f32| s >= 1.0).ok_or_else(invalid)?,
+                _ => (),
+            }
+            if let Some(n) = key.strip_prefix("light") {
+                let n: usize = n.parse().map_err(|_| format!("unknown key lights.{} in config", key))?;
+                if !(1..=MAX_LIGHTS).contains(&n) {
+                    return Err(format!("lights.{}: at most {} lights are supported", key, MAX_LIGHTS));
+                }
+                let (kind, p) = v.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+                let p = floats(p).ok_or_else(invalid)?;
+                let light = match (kind, p.len()) {
+                    ("directional", 6) => Light::Directional {
+                        direction: [p[0], p[1], p[2]],
+                        color: [p[3], p[4], p[5]],
+                    },
+                    ("point", 7) if p[6] >= 0.0 => Light::Point {
+                        position: [p[0], p[1], p[2]],
+                        color: [p[3], p[4], p[5]],
+                        attenuation: p[6],
+                    },
+                    _ => return Err(invalid()),
+                };
+                lights.push((n, light));
+            }
+        }
+        if !lights.is_empty() {
+            lights.sort_by_key(|&(n, _)| n);
+            settings.lights = lights.into_iter().map(|(_, light)| light).collect();
+        }
+        Ok(settings)
+    }
+
+    /// The light casting shadows, the first directional one: its index and
+    /// the direction towards it.
+    pub fn main_light(&self) -> Option<(usize, [f32; 3])> {
+        self.lights.iter().take(MAX_LIGHTS).enumerate().find_map(|(i, light)| match *light {
+            Light::Directional { direction, .. } => Some((i, direction)),
+            Light::Point { .. } => None,
+        })
+    }
+
+    pub fn uniform_array(&self) -> LightsArray {
+        let a = self.ambient;
+        let mut array = [[0.0; 4]; 2 + 2 * MAX_LIGHTS];
+        let shadowed = self.main_light().map_or(-1.0, |(i, _)| i as f32);
+        array[0] = [a[0], a[1], a[2], self.shininess];
+        array[1] = [self.specular, self.lights.len().min(MAX_LIGHTS) as f32, shadowed, 0.0];
+        for (i, light) in self.lights.iter().take(MAX_LIGHTS).enumerate() {
+            let (p, c) = match *light {
+                Light::Directional { direction: d, color: c } => ([d[0], d[1], d[2], 0.0], [c[0], c[1], c[2], 0.0]),
+                Light::Point { position: p, color: c, attenuation } => ([p[0], p[1], p[2], 1.0], [c[0], c[1], c[2], attenuation]),
+            };
+            array[2 + 2 * i] = p;
+            array[3 + 2 * i] = c;
+        }
+        array
+    }
+}
+
+fn floats(v: &str) -> Option<Vec<f32>> {
+    v.split_whitespace().map(|c| c.parse().ok()).collect()
+}
@@ -1,12 +1,11 @@
-use cgmath::conv::array4x4;
-use cgmath::{Euler, Matrix4, Rad, SquareMatrix, Vector3, Vector4, Zero};
-use glium::glutin::event::{ self, Event, VirtualKeyCode, WindowEvent };
+use cgmath::Quaternion;
+use glium::glutin::event::{ self, Event, WindowEvent };
 use glium::glutin::event_loop::ControlFlow;
-use glium::{Display, Program, Surface, uniform};
+use glium::Display;
 use instant::Duration;
 use mandelwow_lib::*;
-use std::f32::consts::PI;
-use std::rc::Rc;
+use mandelwow_lib::keymap::Action;
+use mandelwow_lib::world::{wow, MODEL_Z};
 
 #[cfg(target_os = "emscripten")]
 use std::os::raw::{c_int, c_void};
@@ -26,168 +25,6 @@ fn gl_info(display: &glium::Display) {
     }
 }
 
-const SEA_XSIZE: usize = 40;
-const SEA_ZSIZE: usize = 25;
-
-struct World {
-    mandelwow_program: Rc<Program>,
-    mandelwow_bounds: Cube,
-    mandelwow_bbox: BoundingBox,
-    bounding_box_enabled: bool,
-
-    shaded_cube: ShadedCube,
-    text: Text,
-
-    sea: [[Vector3<f32>; SEA_ZSIZE]; SEA_XSIZE],
-
-    // For the zoom animation synchronized to the drum-hits
-    hit_time: f32,
-    last_hit: f32,
-}
-
-impl World {
-    pub fn new(display: &glium::Display) -> World {
-        let mandelwow_program = Rc::new(mandelwow::program(display));
-        let bounding_box_program = Rc::new(bounding_box::solid_fill_program(display));
-        let shaded_program = Rc::new(shaded_cube::shaded_program(display));
-
-        // These are the bounds for the 3D slice of the 4D Mandelwow
-        let mandelwow_bounds = Cube {
-            xmin: -2.0,
-            xmax: 0.7,
-            ymin: -1.0,
-            ymax: 1.0,
-            zmin: -1.1,
-            zmax: 1.1,
-        };
-
-        // Generate a wavy sea made of cubes
-        let sea_xmin = -20.0f32;
-        let sea_xmax = 20.0f32;
-        let sea_y = -2.5;
-        let sea_zmin = -2.0f32;
-        let sea_zmax = -27.0f32;
-        let sea_xstep = (sea_xmax - sea_xmin) / (SEA_XSIZE as f32);
-        let sea_zstep = (sea_zmax - sea_zmin) / (SEA_ZSIZE as f32);
-        println!("xstep={} ystep={:?}", sea_xstep, sea_zstep);
-
-        let mut sea = [[Vector3::zero(); SEA_ZSIZE]; SEA_XSIZE];
-        #[allow(clippy::needless_range_loop)]
-        for x in 0..SEA_XSIZE {
-            for z in 0..SEA_ZSIZE {
-                sea[x][z] = Vector3 {
-                    x: sea_xmin + (x as f32) * sea_xstep,
-                    y: sea_y,
-                    z: sea_zmin + (z as f32) * sea_zstep,
-                };
-            }
-        }
-
-        World {
-            mandelwow_program,
-            mandelwow_bbox: BoundingBox::new(
-                display, &mandelwow_bounds, bounding_box_program),
-            mandelwow_bounds,
-            bounding_box_enabled: true,
-
-            shaded_cube: ShadedCube::new(display, shaded_program),
-            text: text::Text::new(display),
-            sea,
-
-            hit_time: 0.0,
-            last_hit: 0.0,
-        }
-    }
-
-    fn draw_frame(
-        &self,
-        display: &Display,
-        camera: &support::camera::CameraState,
-        t: f32,
-    ) {
-        let perspview = camera.get_perspview();
-
-        let hit_delta = t - self.hit_time;
-        let hit_scale = 1. / (1. + hit_delta * hit_delta * 15.0) + 1.;
-
-        // Vary the wow factor to slice the Mandelwow along its 4th dimension.
-        let wmin = -0.8;
-        let wmax = 0.8;
-        let wsize = wmax - wmin;
-        let wow = (((t * 0.7).sin() + 1.0) / 2.0) * wsize + wmin;
-
-        //println!("t={} w={:?} camera={:?}", t, w, camera.get_pos());
-
-        let mut frame = display.draw();
-        frame.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
-
-        let rotation = Matrix4::from(Euler {
-            x: Rad(t.sin() / 3.),
-            y: Rad(t.sin() / 2.),
-            z: Rad(t / 1.5),
-        });
-        let z_trans = -3.0; // Send the model back a little bit so it fits the screen.
-        let scale = Matrix4::from_diagonal(Vector4::new(hit_scale, hit_scale, hit_scale, 1.0));
-        let model2 = Matrix4::from_translation(Vector3::unit_z() * z_trans) * rotation * scale;
-        let model = array4x4(model2);
-
-        // Draw the bounding box before the fractal, when the Z-buffer is still clear,
-        // so the lines behind the semi-translucent areas will be drawn.
-        if self.bounding_box_enabled {
-            let uniforms = uniform! {
-                model: model,
-                view:  camera.get_view(),
-                perspective: camera.get_perspective(),
-            };
-            self.mandelwow_bbox.draw(&mut frame, &uniforms);
-        }
-
-        let text_rot = Matrix4::from_angle_x(cgmath::Deg(-90.0f32));
-        let text_pos = Matrix4::from_translation(Vector3 {
-            x: 0.0,
-            y: 0.501,
-            z: 0.0f32,
-        }) * text_rot;
-        for x in 0..SEA_XSIZE {
-            for z in 0..SEA_ZSIZE {
-                let wave = ((x as f32 / SEA_XSIZE as f32 * PI * 5.0 + t * 2.0).sin()
-                    + (z as f32 / SEA_ZSIZE as f32 * PI * 3.0 + t * 3.0).sin())
-                    * 0.3;
-                let model = Matrix4::from_translation(
-                    self.sea[x][z]
-                        + Vector3 {
-                            x: 0.,
-                            y: wave,
-                            z: 0.,
-                        },
-                );
-                let uniforms = uniform! {
-                    model: array4x4(model),
-                    perspview: perspview,
-                    col: [0., (1. - wave).abs() * 0.5,  wave.abs()],
-                };
-                self.shaded_cube.draw(&mut frame, &uniforms);
-                let model = model * text_pos;
-                let c = (x + z * SEA_XSIZE) as u8 as char;
-                self.text.draw(&mut frame, c, &model, &perspview);
-            }
-        }
-
-        mandelwow::draw(
-            &display,
-            &mut frame,
-            &self.mandelwow_program,
-            model,
-            &camera,
-            &self.mandelwow_bounds,
-            wow,
-        );
-
-        frame.finish().unwrap();
-    }
-}
-
-
 #[cfg(target_os = "emscripten")]
 #[allow(non_camel_case_types)]
 type em_callback_func = unsafe extern "C" fn();
@@ -232,6 +69,17 @@ where
     support::start_loop(callback);
 }
 
+fn grab_mouse(display: &Display, camera: &mut support::camera::CameraState, grab: bool) {
+    let gl_window = display.gl_window();
+    let window = gl_window.window();
+    if let Err(err) = window.set_cursor_grab(grab) {
+        println!("Couldn't grab the mouse pointer: {:?}", err);
+        return;
+    }
+    window.set_cursor_visible(!grab);
+    camera.set_mouse_grabbed(grab);
+}
+
 //extern crate gleam;
 
 /*
@@ -251,30 +99,153 @@ fn main() {
     gl.glGetInternalformativ(0, 0, 0, 0, 0);
     */
 
-    let mut soundplayer = sound::start();
+    let mut config = Config::load();
+    let mut options = match Options::from_args(&config) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("mandelwow: {}\n\n{}", err, options::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        print!("{}", options::USAGE);
+        return;
+    }
+    let restored = options.restore.as_ref().map(|file| {
+        match screenshot::load_scene_info(std::path::Path::new(file)) {
+            Ok(info) => info,
+            Err(err) => {
+                eprintln!("mandelwow: can't restore {}: {}", file, err);
+                std::process::exit(1);
+            }
+        }
+    });
+    if let Some(ref info) = restored {
+        options.start_time = info.t;
+        if info.version != env!("CARGO_PKG_VERSION") {
+            println!("Restoring a screenshot from MandelWow {}, the picture may differ", info.version);
+        }
+    }
+
+    let sdl_context = sdl2::init().unwrap();
+    let mut soundplayer = sound::start(&sdl_context, &sound::PlayOptions {
+        filename: &options.module,
+        device: options.audio_device.as_deref(),
+        mute: options.mute,
+        start_time: options.start_time,
+        looping: options.looping,
+    });
 
     let event_loop = glutin::event_loop::EventLoop::new();
+    let monitor = options.monitor.and_then(|i| event_loop.available_monitors().nth(i));
+    let fullscreen_mode = Some(glium::glutin::window::Fullscreen::Borderless(monitor));
+    let mut fullscreen = options.fullscreen;
     let window = glutin::window::WindowBuilder::new()
-        //.with_dimensions(1280, 720)
+        .with_inner_size(glutin::dpi::LogicalSize::new(options.width, options.height))
+        .with_fullscreen(if fullscreen { fullscreen_mode.clone() } else { None })
         .with_title("MandelWow");
     let context = glutin::ContextBuilder::new()
         //.with_gl_profile(glutin::GlProfile::Core)
-        //.with_gl(glutin::GlRequest::Specific(glutin::Api::WebGl, (2, 0)))
-        .with_gl(glutin::GlRequest::Specific(
-            glutin::Api::OpenGlEs,
-            (3, 0),
-        ))
-        //.with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (4, 0)))
+        .with_gl(options.gl_request())
         //.with_depth_buffer(24)
-        .with_vsync(true);
+        .with_vsync(options.vsync);
 
     let display = glium::Display::new(window, context, &event_loop).unwrap();
     gl_info(&display);
-    let mut world = World::new(&display);
+    let mut world = World::new(&display, &options);
 
     let mut timer = Timer::new();
+    // The tempo reached after fast-forwarding applies from the start.
+    if let Some((bpm, speed)) = sound::playing_speed(&mut soundplayer) {
+        timer.set_tempo(bpm, speed);
+    }
+    timer.t = options.start_time;
+    let keymap = match Keymap::from_config(&config) {
+        Ok(keymap) => keymap,
+        Err(err) => {
+            eprintln!("mandelwow: {}", err);
+            std::process::exit(2);
+        }
+    };
+    let mut gamepad = match GamepadSettings::from_config(&config)
+        .and_then(|settings| Gamepad::new(&sdl_context, settings))
+    {
+        Ok(gamepad) => Some(gamepad),
+        Err(err) => {
+            println!("Gamepad support disabled: {}", err);
+            None
+        }
+    };
+    let camera_path_file = std::path::PathBuf::from(&options.camera_path);
+    let mut camera_path = match CameraPath::load(&camera_path_file) {
+        Ok(path) => path,
+        Err(err) => {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                println!("Couldn't load camera path {}: {}", camera_path_file.display(), err);
+            }
+            CameraPath::new()
+        }
+    };
+    let mut path_playback = options.play_path;
+
+    let bookmarks_file = options.bookmarks.as_ref().map(std::path::PathBuf::from)
+        .or_else(|| Some(Config::default_path()?.parent()?.join("bookmarks")));
+    let mut bookmarks = match bookmarks_file.as_deref().map(Bookmarks::load) {
+        Some(Ok(bookmarks)) => bookmarks,
+        Some(Err(err)) if err.kind() != std::io::ErrorKind::NotFound => {
+            println!("Couldn't load bookmarks {}: {}", bookmarks_file.as_ref().unwrap().display(), err);
+            Bookmarks::new()
+        }
+        _ => Bookmarks::new(),
+    };
+    let mut storing_bookmark = false;
+
+    let screenshot_settings = match screenshot::ScreenshotSettings::from_config(&config) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("mandelwow: {}", err);
+            std::process::exit(2);
+        }
+    };
+
     let mut camera = support::camera::CameraState::new();
-    let mut fullscreen = false;
+    camera.set_orbit_target(support::vec3::Vec3(0.0, 0.0, MODEL_Z));
+    camera.speed = match support::camera::CameraSpeed::from_config(&config) {
+        Ok(speed) => speed,
+        Err(err) => {
+            eprintln!("mandelwow: {}", err);
+            std::process::exit(2);
+        }
+    };
+    match support::camera::Lens::from_config(&config) {
+        Ok(lens) => camera.set_lens(lens),
+        Err(err) => {
+            eprintln!("mandelwow: {}", err);
+            std::process::exit(2);
+        }
+    }
+    world.resize(&display, &mut camera);
+    if let Some(pos) = config.get_vec3("camera", "pos") {
+        camera.set_pos(pos);
+    }
+    if let Some(dir) = config.get_vec3("camera", "dir") {
+        camera.set_dir(dir);
+    }
+    if let Some(&[w, x, y, z]) = config.get_floats("camera", "orientation").as_deref() {
+        camera.set_orientation(Quaternion::new(w, x, y, z));
+    }
+    if let Some(info) = restored {
+        camera.set_pos(info.pos);
+        camera.set_orientation(info.orientation);
+        camera.set_fov(info.fov);
+        world.hit_time = info.hit_time;
+        // Each slice is a draw call: keep a corrupt file from asking for millions.
+        let (min, max) = (options::Quality::Low.slices(), options::Quality::High.slices());
+        world.mandelwow_slices = info.slices.clamp(min, max);
+        path_playback = false;
+        timer.pause = true;
+        println!("Restored the view at t={:.2}, press P to resume", info.t);
+    }
 
     event_loop.run(move |event, _, control_flow| {
         let t = timer.t;
@@ -283,14 +254,64 @@ fn main() {
             world.hit_time = t;
         }
         world.last_hit = new_hit;
-
-        camera.update();
+        // Follow the tempo changes of the module.
+        if let Some((bpm, speed)) = sound::playing_speed(&mut soundplayer) {
+            timer.set_tempo(bpm, speed);
+        }
 
         *control_flow = ControlFlow::WaitUntil(timer.now + Duration::from_nanos(16_666_667));
         match event {
+            Event::LoopDestroyed => {
+                // Keep what was changed while running: the window, the display
+                // toggles and the view. Settings left as they started keep their
+                // value in the file, so one-off command-line options don't stick.
+                if !fullscreen {
+                    let window = display.gl_window();
+                    let window = window.window();
+                    let size = window.inner_size().to_logical::<u32>(window.scale_factor());
+                    let size = (size.width, size.height);
+                    if size.0 > 0 && size.1 > 0 && size != (options.width, options.height) {
+                        config.set("display", "size", format!("{}x{}", size.0, size.1));
+                    }
+                }
+                if fullscreen != options.fullscreen {
+                    config.set("display", "fullscreen", fullscreen);
+                }
+                if world.letterbox != options.letterbox {
+                    config.set("display", "letterbox", world.letterbox);
+                }
+                if world.retro != options.retro {
+                    config.set("display", "retro", world.retro);
+                }
+                config.set_vec3("camera", "pos", camera.get_pos());
+                config.set_vec3("camera", "dir", camera.get_dir());
+                let q = camera.get_orientation();
+                config.set_floats("camera", "orientation", &[q.s, q.v.x, q.v.y, q.v.z]);
+                if let Err(err) = config.save() {
+                    println!("Couldn't save config: {}", err);
+                }
+            }
             Event::MainEventsCleared => {
+                if let Some(ref mut gamepad) = gamepad {
+                    let pad = gamepad.poll();
+                    camera.set_analog(pad.strafe, pad.up, pad.forward, pad.look_x, pad.look_y);
+                }
                 timer.update();
-                world.draw_frame(&display, &camera, t);
+                // Past the last keyframe the camera is free again, from where the path left it.
+                let path_end = camera_path.keys().last().map_or(f32::NEG_INFINITY, |key| key.t);
+                match camera_path.sample(timer.t) {
+                    Some(key) if path_playback && timer.t <= path_end => {
+                        camera.look_at(key.pos, key.target);
+                        camera.set_fov(key.fov);
+                    }
+                    _ => camera.update(timer.dt),
+                }
+                let mut frame = display.draw();
+                world.draw_frame(&display, &mut frame, &camera, t);
+                frame.finish().unwrap();
+                if sound::finished(&mut soundplayer) {
+                    *control_flow = ControlFlow::Exit;
+                }
             }
             Event::NewEvents(cause) => {
                 match cause {
@@ -300,39 +321,105 @@ fn main() {
                     _ => {}
                 }
             }
+            Event::DeviceEvent { event: event::DeviceEvent::MouseMotion { delta }, .. } => {
+                camera.process_mouse_motion(delta.0, delta.1);
+            }
             Event::WindowEvent { event, .. } => {
                 camera.process_input(&event);
                 match event {
                     WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                    WindowEvent::KeyboardInput { input, .. } => {
-                        if input.state == event::ElementState::Pressed {
-                            if let Some(key) = input.virtual_keycode {
-                                match key {
-                                    VirtualKeyCode::Escape | VirtualKeyCode::Q => {
-                                        *control_flow = ControlFlow::Exit;
-                                    }
-                                    VirtualKeyCode::B => world.bounding_box_enabled ^= true,
-                                    VirtualKeyCode::P => timer.pause ^= true,
-                                    VirtualKeyCode::PageUp => timer.t += 0.1,
-                                    VirtualKeyCode::PageDown => timer.t -= 0.2,
-                                    VirtualKeyCode::F10 => screenshot::take_screenshot(&display),
-                                    VirtualKeyCode::F11 | VirtualKeyCode::Return => {
-                                        fullscreen ^= true;
-                                        let fs = if fullscreen {
-                                            // let monitor_handle = display.gl_window().window()
-                                            //    .available_monitors().next().unwrap();
-                                            Some(glium::glutin::window::Fullscreen::Borderless(None))
-                                        } else {
-                                            None
-                                        };
-                                        display.gl_window().window().set_fullscreen(fs);
+                    WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                        world.resize(&display, &mut camera);
+                    }
+                    WindowEvent::Focused(false) if camera.is_mouse_grabbed() => {
+                        grab_mouse(&display, &mut camera, false);
+                    }
+                    _ => (),
+                }
+                if let Some((action, pressed)) = keymap.translate(&event) {
+                    camera.process_action(action, pressed);
+                    if action == Action::StoreBookmark {
+                        storing_bookmark = pressed;
+                    }
+                    if pressed {
+                        match action {
+                            Action::Quit => *control_flow = ControlFlow::Exit,
+                            Action::ToggleBoundingBox => world.bounding_box_enabled ^= true,
+                            Action::TogglePause => timer.pause ^= true,
+                            Action::SeekForward => timer.t += 0.1,
+                            Action::SeekBackward => timer.t -= 0.2,
+                            Action::Screenshot => {
+                                match world.screenshot(&display, &camera, t, &screenshot_settings) {
+                                    Ok(path) => println!("Saved screenshot {}", path.display()),
+                                    Err(err) => println!("Couldn't save screenshot: {}", err),
+                                }
+                            }
+                            Action::ToggleFullscreen => {
+                                fullscreen ^= true;
+                                let fs = if fullscreen {
+                                    fullscreen_mode.clone()
+                                } else {
+                                    None
+                                };
+                                display.gl_window().window().set_fullscreen(fs);
+                            }
+                            Action::ToggleLetterbox => {
+                                world.letterbox ^= true;
+                                world.resize(&display, &mut camera);
+                            }
+                            Action::ToggleRetro => {
+                                world.retro ^= true;
+                                world.resize(&display, &mut camera);
+                            }
+                            Action::RecordKeyframe => {
+                                camera_path.add(camera_path::Keyframe {
+                                    t: timer.t,
+                                    pos: camera.get_pos(),
+                                    target: camera.get_target(),
+                                    fov: camera.get_fov(),
+                                });
+                                println!("Recorded camera keyframe at t={:.2}", timer.t);
+                                if let Err(err) = camera_path.save(&camera_path_file) {
+                                    println!("Couldn't save camera path {}: {}",
+                                             camera_path_file.display(), err);
+                                }
+                            }
+                            Action::TogglePathPlayback => path_playback ^= true,
+                            Action::Bookmark(slot) if storing_bookmark => {
+                                bookmarks.set(slot, bookmarks::Bookmark {
+                                    pos: camera.get_pos(),
+                                    orientation: camera.get_orientation(),
+                                    fov: camera.get_fov(),
+                                    t: timer.t,
+                                    wow: wow(timer.t),
+                                });
+                                println!("Stored bookmark {}", slot);
+                                if let Some(ref file) = bookmarks_file {
+                                    if let Err(err) = bookmarks.save(file) {
+                                        println!("Couldn't save bookmarks {}: {}", file.display(), err);
                                     }
-                                    _ => (),
                                 }
                             }
+                            Action::Bookmark(slot) => match bookmarks.get(slot) {
+                                Some(b) => {
+                                    // Orbit mode and path playback would move the camera away.
+                                    path_playback = false;
+                                    camera.set_mode(support::camera::CameraMode::Free);
+                                    camera.set_pos(b.pos);
+                                    camera.set_orientation(b.orientation);
+                                    camera.set_fov(b.fov);
+                                    timer.t = b.t;
+                                    println!("Recalled bookmark {} (t={:.2}, wow={:.3})", slot, b.t, b.wow);
+                                }
+                                None => println!("Bookmark {} is empty", slot),
+                            },
+                            Action::ToggleMouseLook => {
+                                let grab = !camera.is_mouse_grabbed();
+                                grab_mouse(&display, &mut camera, grab);
+                            }
+                            _ => (),
                         }
                     }
-                    _ => (),
                 }
             },
             _ => (),
@@ -3,8 +3,8 @@
 use crate::cube::Cube;
 use glium;
 use glium::index::PrimitiveType;
-use glium::{Display, Program, Surface, implement_vertex, uniform};
-use crate::support;
+use glium::backend::Facade;
+use glium::{Program, Surface, implement_vertex, uniform};
 
 /*
 fn mand(cx: f32, cy: f32) -> [f32; 3] {
@@ -27,20 +27,33 @@ fn mand(cx: f32, cy: f32) -> [f32; 3] {
 }
 */
 
-pub fn program(display: &Display) -> Program {
-    Program::from_source(
-            display,
-            include_str!("shaders/mandelwow.vert"),
-            include_str!("shaders/mandelwow.frag"), None)
+// Fractal settings, as implemented by shaders/mandelwow.frag.
+pub const FORMULA: &str = "z = z^2 + c, c = (x, y), z0 = (wow, z)";
+pub const MAX_ITER: u32 = 64;
+
+fn fractal_program<F: ?Sized + Facade>(display: &F, fragment_shader: &str) -> Program {
+    let fragment_shader_src = [include_str!("shaders/mandelwow.glsl"), fragment_shader].concat();
+    Program::from_source(display, include_str!("shaders/mandelwow.vert"), &fragment_shader_src, None)
         .unwrap()
 }
 
-fn mandel<U>(display: &Display,
-          frame: &mut glium::Frame,
+pub fn program<F: ?Sized + Facade>(display: &F) -> Program {
+    fractal_program(display, include_str!("shaders/mandelwow.frag"))
+}
+
+/// Draws the depth of the fractal into a shadow map, leaving out the most
+/// transparent areas.
+pub fn shadow_program<F: ?Sized + Facade>(display: &F) -> Program {
+    fractal_program(display, &[include_str!("shaders/mandelwow_shadow.frag"),
+                               include_str!("shaders/pack_depth.glsl")].concat())
+}
+
+fn mandel<F, S, U>(display: &F,
+          frame: &mut S,
           program: &Program,
           uniforms: &U,
           bounds: &Cube,
-          z: [f32; 2]) where U: glium::uniforms::Uniforms {
+          z: [f32; 2]) where F: ?Sized + Facade, S: Surface, U: glium::uniforms::Uniforms {
 
     #[derive(Copy, Clone)]
     struct Vertex {
@@ -103,15 +116,17 @@ fn mandel<U>(display: &Display,
     frame.draw(&vb, &indices, program, uniforms, &params).unwrap();
 }
 
-pub fn draw(display: &Display,
-             mut frame: &mut glium::Frame,
+#[allow(clippy::too_many_arguments)]
+pub fn draw<F: ?Sized + Facade, S: Surface>(display: &F,
+             frame: &mut S,
              program: &Program,
              model: [[f32; 4]; 4],
-             camera: &support::camera::CameraState,
+             view: [[f32; 4]; 4],
+             perspective: [[f32; 4]; 4],
              bounds: &Cube,
-             mandel_w: f32) {
+             mandel_w: f32,
+             zres: u32) {
     let mut z0 = [mandel_w, 0f32];
-    let zres = 30;
     let zmin = bounds.zmin;
     let zmax = bounds.zmax;
     let zstep = (zmax - zmin) / zres as f32;
@@ -124,10 +139,10 @@ pub fn draw(display: &Display,
         let uniforms = uniform! {
             z0: z0,
             model: model,
-            view:  camera.get_view(),
-            perspective: camera.get_perspective(),
+            view:  view,
+            perspective: perspective,
         };
 
-        mandel(display, &mut frame, program, &uniforms, bounds, z0);
+        mandel(display, frame, program, &uniforms, bounds, z0);
     }
 }
@@ -0,0 +1,343 @@
+// Command-line options for the mandelwow binary.
+//
+// Settings are taken from the defaults, then from the user configuration
+// file (see config.rs) and finally from the command line.
+
+use crate::config::Config;
+use crate::lights::Lights;
+use crate::postprocess::PostScenes;
+use crate::retro::RetroSettings;
+use crate::sea::SeaSettings;
+use glium::glutin;
+use std::fmt;
+
+pub const USAGE: &str = "\
+Usage: mandelwow [OPTIONS]
+
+Options:
+  -h, --help                 Print this help and exit
+  -s, --size WIDTHxHEIGHT    Window size (default: 1280x720)
+  -f, --fullscreen           Start in fullscreen mode
+      --monitor N            Go fullscreen on monitor N (0-based)
+      --vsync on|off         Synchronize with the display refresh (default: on)
+      --letterbox            Keep a 16:9 picture with black bars around it
+      --retro                Show the picture like a Commodore 64 would
+  -m, --module FILE          XM module to play (default: flora.xm)
+  -t, --start SECONDS        Start playing from the given time
+      --loop / --no-loop     Keep playing when the module ends (default: loop)
+      --gl API:MAJOR.MINOR   OpenGL API and version: gl, gles or webgl (default: gles:3.0)
+      --mute                 Don't play any sound
+      --audio-device NAME    Play sound on the named audio device
+  -q, --quality PRESET       Rendering quality: low, medium or high (default: medium)
+      --seed N               Seed for the pseudo-random parts of the demo
+      --camera-path FILE     Camera path to play back and record into (default: camera.path)
+      --play-path            Fly along the camera path from the start (L toggles it)
+      --bookmarks FILE       Camera bookmarks file (default: next to the config file)
+      --restore FILE.png     Go back to the view saved in a screenshot
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlApi {
+    Gl,
+    GlEs,
+    WebGl,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quality {
+    Low,
+    Medium,
+    High,
+}
+
+impl Quality {
+    /// Number of slices used to draw the fractal.
+    pub fn slices(self) -> u32 {
+        match self {
+            Quality::Low => 15,
+            Quality::Medium => 30,
+            Quality::High => 60,
+        }
+    }
+
+    /// Width and height of the shadow map.
+    pub fn shadow_map_size(self) -> u32 {
+        match self {
+            Quality::Low => 1024,
+            Quality::Medium => 2048,
+            Quality::High => 4096,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub help: bool,
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub monitor: Option<usize>,
+    pub vsync: bool,
+    pub letterbox: bool,
+    pub retro: bool,
+    pub module: String,
+    pub start_time: f32,
+    pub looping: bool,
+    pub gl_api: GlApi,
+    pub gl_version: (u8, u8),
+    pub mute: bool,
+    pub audio_device: Option<String>,
+    pub quality: Quality,
+    pub seed: Option<u64>,
+    pub camera_path: String,
+    pub play_path: bool,
+    pub bookmarks: Option<String>,
+    pub restore: Option<String>,
+    pub sea: SeaSettings,
+    pub lights: Lights,
+    pub post: PostScenes,
+    pub retro_filter: RetroSettings,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            help: false,
+            width: 1280,
+            height: 720,
+            fullscreen: false,
+            monitor: None,
+            vsync: true,
+            letterbox: false,
+            retro: false,
+            module: "flora.xm".to_string(),
+            start_time: 0.0,
+            looping: true,
+            gl_api: GlApi::GlEs,
+            gl_version: (3, 0),
+            mute: false,
+            audio_device: None,
+            quality: Quality::Medium,
+            seed: None,
+            camera_path: "camera.path".to_string(),
+            play_path: false,
+            bookmarks: None,
+            restore: None,
+            sea: SeaSettings::default(),
+            lights: Lights::default(),
+            post: PostScenes::default(),
+            retro_filter: RetroSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_size(s: &str) -> Option<(u32, u32)> {
+    let mut it = s.splitn(2, 'x');
+    let w = it.next()?.parse().ok()?;
+    let h = it.next()?.parse().ok()?;
+    if w == 0 || h == 0 {
+        return None;
+    }
+    Some((w, h))
+}
+
+/// The booleans of the command line and the config file: on/off, yes/no,
+/// true/false or 1/0.
+pub(crate) fn parse_bool(s: &str) -> Option<bool> {
+    match s {
+        "on" | "yes" | "true" | "1" => Some(true),
+        "off" | "no" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_quality(s: &str) -> Option<Quality> {
+    match s {
+        "low" => Some(Quality::Low),
+        "medium" => Some(Quality::Medium),
+        "high" => Some(Quality::High),
+        _ => None,
+    }
+}
+
+fn parse_gl(s: &str) -> Option<(GlApi, (u8, u8))> {
+    let mut it = s.splitn(2, ':');
+    let api = match it.next()? {
+        "gl" => GlApi::Gl,
+        "gles" => GlApi::GlEs,
+        "webgl" => GlApi::WebGl,
+        _ => return None,
+    };
+    let mut version = it.next()?.splitn(2, '.');
+    let major = version.next()?.parse().ok()?;
+    let minor = match version.next() {
+        Some(minor) => minor.parse().ok()?,
+        None => 0,
+    };
+    Some((api, (major, minor)))
+}
+
+impl Options {
+    /// Parse the process arguments on top of the user configuration.
+    pub fn from_args(config: &Config) -> Result<Options, ParseError> {
+        let mut options = Options::default();
+        options.apply_config(config)?;
+        options.parse_args(std::env::args().skip(1))?;
+        Ok(options)
+    }
+
+    /// Override the current settings with those found in the configuration file.
+    pub fn apply_config(&mut self, config: &Config) -> Result<(), ParseError> {
+        let invalid = |section: &str, key: &str, v: &str| {
+            ParseError(format!("invalid value for {}.{} in config: {}", section, key, v))
+        };
+        for (key, v) in config.section("display") {
+            match key {
+                "size" => {
+                    let (w, h) = parse_size(v).ok_or_else(|| invalid("display", key, v))?;
+                    self.width = w;
+                    self.height = h;
+                }
+                "fullscreen" => self.fullscreen = parse_bool(v).ok_or_else(|| invalid("display", key, v))?,
+                "monitor" => self.monitor = Some(v.parse().map_err(|_| invalid("display", key, v))?),
+                "vsync" => self.vsync = parse_bool(v).ok_or_else(|| invalid("display", key, v))?,
+                "letterbox" => self.letterbox = parse_bool(v).ok_or_else(|| invalid("display", key, v))?,
+                "retro" => self.retro = parse_bool(v).ok_or_else(|| invalid("display", key, v))?,
+                "gl" => {
+                    let (api, version) = parse_gl(v).ok_or_else(|| invalid("display", key, v))?;
+                    self.gl_api = api;
+                    self.gl_version = version;
+                }
+                _ => (),
+            }
+        }
+        for (key, v) in config.section("audio") {
+            match key {
+                "module" => self.module = v.to_string(),
+                "device" => self.audio_device = if v.is_empty() { None } else { Some(v.to_string()) },
+                "mute" => self.mute = parse_bool(v).ok_or_else(|| invalid("audio", key, v))?,
+                "loop" => self.looping = parse_bool(v).ok_or_else(|| invalid("audio", key, v))?,
+                _ => (),
+            }
+        }
+        for (key, v) in config.section("render") {
+            match key {
+                "quality" => self.quality = parse_quality(v).ok_or_else(|| invalid("render", key, v))?,
+                "seed" => self.seed = Some(v.parse().map_err(|_| invalid("render", key, v))?),
+                _ => (),
+            }
+        }
+        if let Some(path) = config.get("camera", "path") {
+            self.camera_path = path.to_string();
+        }
+        if let Some(v) = config.get("camera", "play") {
+            self.play_path = parse_bool(v).ok_or_else(|| invalid("camera", "play", v))?;
+        }
+        if let Some(path) = config.get("camera", "bookmarks") {
+            self.bookmarks = Some(path.to_string());
+        }
+        self.sea = SeaSettings::from_config(config).map_err(ParseError)?;
+        self.lights = Lights::from_config(config).map_err(ParseError)?;
+        self.post = PostScenes::from_config(config).map_err(ParseError)?;
+        self.retro_filter = RetroSettings::from_config(config).map_err(ParseError)?;
+        Ok(())
+    }
+
+    /// Override the current settings with command-line arguments,
+    /// not including the program name.
+    pub fn parse_args<I, S>(&mut self, args: I) -> Result<(), ParseError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            // Accept both "--opt value" and "--opt=value".
+            let (opt, inline_value) = match arg.find('=') {
+                Some(i) if arg.starts_with("--") => (arg[..i].to_string(), Some(arg[i + 1..].to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || -> Result<String, ParseError> {
+                match inline_value.clone().or_else(|| args.next()) {
+                    Some(v) => Ok(v),
+                    None => Err(ParseError(format!("missing value for {}", opt))),
+                }
+            };
+            let invalid = |v: &str| ParseError(format!("invalid value for {}: {}", opt, v));
+
+            match opt.as_str() {
+                "-h" | "--help" => self.help = true,
+                "-s" | "--size" => {
+                    let v = value()?;
+                    let (w, h) = parse_size(&v).ok_or_else(|| invalid(&v))?;
+                    self.width = w;
+                    self.height = h;
+                }
+                "-f" | "--fullscreen" => self.fullscreen = true,
+                "--monitor" => {
+                    let v = value()?;
+                    self.monitor = Some(v.parse().map_err(|_| invalid(&v))?);
+                    self.fullscreen = true;
+                }
+                "--vsync" => {
+                    let v = value()?;
+                    self.vsync = parse_bool(&v).ok_or_else(|| invalid(&v))?;
+                }
+                "--letterbox" => self.letterbox = true,
+                "--retro" => self.retro = true,
+                "-m" | "--module" => self.module = value()?,
+                "-t" | "--start" => {
+                    let v = value()?;
+                    self.start_time = v.parse().map_err(|_| invalid(&v))?;
+                    if !self.start_time.is_finite() || self.start_time < 0.0 {
+                        return Err(invalid(&v));
+                    }
+                }
+                "--loop" => self.looping = true,
+                "--no-loop" => self.looping = false,
+                "--gl" => {
+                    let v = value()?;
+                    let (api, version) = parse_gl(&v).ok_or_else(|| invalid(&v))?;
+                    self.gl_api = api;
+                    self.gl_version = version;
+                }
+                "--mute" => self.mute = true,
+                "--audio-device" => self.audio_device = Some(value()?),
+                "-q" | "--quality" => {
+                    let v = value()?;
+                    self.quality = parse_quality(&v).ok_or_else(|| invalid(&v))?;
+                }
+                "--seed" => {
+                    let v = value()?;
+                    self.seed = Some(v.parse().map_err(|_| invalid(&v))?);
+                }
+                "--camera-path" => self.camera_path = value()?,
+                "--play-path" => self.play_path = true,
+                "--bookmarks" => self.bookmarks = Some(value()?),
+                "--restore" => self.restore = Some(value()?),
+                _ => return Err(ParseError(format!("unknown option: {}", arg))),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn gl_request(&self) -> glutin::GlRequest {
+        let api = match self.gl_api {
+            GlApi::Gl => glutin::Api::OpenGl,
+            GlApi::GlEs => glutin::Api::OpenGlEs,
+            GlApi::WebGl => glutin::Api::WebGl,
+        };
+        glutin::GlRequest::Specific(api, self.gl_version)
+    }
+}
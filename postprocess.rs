@@ -0,0 +1,395 @@
+// Post-processing: the scene is rendered into an HDR texture (see
+// RenderTarget::new_hdr), which goes through a chain of full-window passes
+// (shaders/post_*.frag) and is finally tonemapped to the window.
+//
+// The chain is set in the [postprocess] section of the config file:
+//
+//   [postprocess]
+//   passes = bloom, vignette         # In this order, none by default
+//   exposure = 1
+//   tonemap = aces                   # aces, reinhard or clamp (the default)
+//   # Parameters of each pass, used only when it's in the list:
+//   bloom = 1 0.6                    # threshold intensity
+//   vignette = 0.3 0.75              # strength radius
+//   chromatic_aberration = 0.004     # offset at the corners, fraction of the width
+//   film_grain = 0.04                # strength
+//   color_grading = 1.1 1.2  1 1 1   # contrast saturation  r g b gains
+//
+// Scenes of the demo can have their own chain, from the time they start
+// until the next one, in a section named after them. The keys they don't set
+// are those of [postprocess]:
+//
+//   [postprocess.finale]
+//   start = 95.5                     # Seconds into the demo
+//   passes = bloom, film_grain
+//   bloom = 0.8 1.2
+//
+// The passes see linear colors which may exceed 1, before tonemapping,
+// where the GL implementation can render into half floats (see
+// render_target::hdr_format).
+
+use crate::config::Config;
+use crate::render_target;
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::{MipmapsOption, Texture2d};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction, Uniforms};
+use glium::{Program, Rect, Surface, VertexBuffer, implement_vertex, uniform};
+use std::cell::RefCell;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pass {
+    /// Glow around what's brighter than the threshold.
+    Bloom { threshold: f32, intensity: f32 },
+    /// Darker corners, starting at radius (1 is the middle of the edges).
+    Vignette { strength: f32, radius: f32 },
+    ChromaticAberration { amount: f32 },
+    FilmGrain { amount: f32 },
+    ColorGrading { contrast: f32, saturation: f32, gain: [f32; 3] },
+}
+
+impl Pass {
+    const NAMES: [&'static str; 5] = ["bloom", "vignette", "chromatic_aberration", "film_grain", "color_grading"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::Bloom { .. } => "bloom",
+            Pass::Vignette { .. } => "vignette",
+            Pass::ChromaticAberration { .. } => "chromatic_aberration",
+            Pass::FilmGrain { .. } => "film_grain",
+            Pass::ColorGrading { .. } => "color_grading",
+        }
+    }
+
+    /// The pass with its default parameters.
+    pub fn from_name(name: &str) -> Option<Pass> {
+        match name {
+            "bloom" => Some(Pass::Bloom { threshold: 1.0, intensity: 0.6 }),
+            "vignette" => Some(Pass::Vignette { strength: 0.3, radius: 0.75 }),
+            "chromatic_aberration" => Some(Pass::ChromaticAberration { amount: 0.004 }),
+            "film_grain" => Some(Pass::FilmGrain { amount: 0.04 }),
+            "color_grading" => Some(Pass::ColorGrading { contrast: 1.0, saturation: 1.0, gain: [1.0; 3] }),
+            _ => None,
+        }
+    }
+
+    // All of them, with their default parameters.
+    fn defaults() -> Vec<Pass> {
+        Pass::NAMES.iter().filter_map(|name| Pass::from_name(name)).collect()
+    }
+
+    // The same pass with the parameters of the config file.
+    fn with_params(self, p: &[f32]) -> Option<Pass> {
+        match (self, p) {
+            (Pass::Bloom { .. }, &[threshold, intensity]) => Some(Pass::Bloom { threshold, intensity }),
+            (Pass::Vignette { .. }, &[strength, radius]) => Some(Pass::Vignette { strength, radius }),
+            (Pass::ChromaticAberration { .. }, &[amount]) => Some(Pass::ChromaticAberration { amount }),
+            (Pass::FilmGrain { .. }, &[amount]) => Some(Pass::FilmGrain { amount }),
+            (Pass::ColorGrading { .. }, &[contrast, saturation, r, g, b]) =>
+                Some(Pass::ColorGrading { contrast, saturation, gain: [r, g, b] }),
+            _ => None,
+        }
+    }
+}
+
+/// How HDR colors are brought down to what the window can show.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tonemap {
+    Clamp = 0,
+    Reinhard = 1,
+    Aces = 2,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostSettings {
+    pub passes: Vec<Pass>,
+    pub exposure: f32,
+    pub tonemap: Tonemap,
+}
+
+impl Default for PostSettings {
+    fn default() -> PostSettings {
+        PostSettings {
+            passes: Vec::new(),
+            exposure: 1.0,
+            tonemap: Tonemap::Clamp,
+        }
+    }
+}
+
+impl PostSettings {
+    /// The chain of the [postprocess] section.
+    pub fn from_config(config: &Config) -> Result<PostSettings, String> {
+        let (settings, _) = PostSettings::parse("postprocess", &config.section("postprocess"),
+                                                &PostSettings::default(), &Pass::defaults())?;
+        Ok(settings)
+    }
+
+    // The chain of a section, for the keys it doesn't set the one of base,
+    // with params as the parameters of the passes. Also returns the
+    // parameters of this section, for the scenes to start from.
+    fn parse(section: &str, keys: &[(&str, &str)], base: &PostSettings, params: &[Pass])
+            -> Result<(PostSettings, Vec<Pass>), String> {
+        let mut settings = base.clone();
+        let mut params = params.to_vec();
+        let mut names = None;
+        for &(key, v) in keys {
+            let invalid = || format!("invalid value for {}.{} in config: {}", section, key, v);
+            match key {
+                "passes" => names = Some(v),
+                "exposure" => settings.exposure = v.parse().ok().filter(|&e: &f32| e > 0.0).ok_or_else(invalid)?,
+                "tonemap" => settings.tonemap = match v {
+                    "clamp" => Tonemap::Clamp,
+                    "reinhard" => Tonemap::Reinhard,
+                    "aces" => Tonemap::Aces,
+                    _ => return Err(invalid()),
+                },
+                _ => {
+                    let pass = params.iter_mut().find(|pass| pass.name() == key)
+                        .ok_or_else(|| format!("unknown key {}.{} in config", section, key))?;
+                    let p: Vec<f32> = v.split_whitespace().map(|c| c.parse().ok()).collect::<Option<_>>()
+                        .ok_or_else(invalid)?;
+                    *pass = pass.with_params(&p).ok_or_else(invalid)?;
+                }
+            }
+        }
+        // Without a list of its own, the passes of base with our parameters.
+        let names: Vec<&str> = match names {
+            Some(names) => names.split(',').map(str::trim).filter(|s| !s.is_empty()).collect(),
+            None => base.passes.iter().map(Pass::name).collect(),
+        };
+        settings.passes = names
+            .into_iter()
+            .map(|name| params.iter().find(|pass| pass.name() == name).copied()
+                .ok_or_else(|| format!("unknown pass in {}.passes: {}", section, name)))
+            .collect::<Result<_, _>>()?;
+        Ok((settings, params))
+    }
+}
+
+/// A part of the demo with its own chain, from `start` until the next one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostScene {
+    pub name: String,
+    /// In seconds.
+    pub start: f32,
+    pub settings: PostSettings,
+}
+
+/// The chain of the whole demo, and of the scenes which have their own.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PostScenes {
+    pub default: PostSettings,
+    /// By start time.
+    pub scenes: Vec<PostScene>,
+}
+
+impl From<PostSettings> for PostScenes {
+    fn from(default: PostSettings) -> PostScenes {
+        PostScenes { default, scenes: Vec::new() }
+    }
+}
+
+impl PostScenes {
+    /// [postprocess], and a [postprocess.NAME] section per scene.
+    pub fn from_config(config: &Config) -> Result<PostScenes, String> {
+        let (default, params) = PostSettings::parse("postprocess", &config.section("postprocess"),
+                                                    &PostSettings::default(), &Pass::defaults())?;
+        let mut scenes = Vec::new();
+        for section in config.section_names() {
+            let name = match section.strip_prefix("postprocess.") {
+                Some(name) => name,
+                None => continue,
+            };
+            let keys = config.section(section);
+            let start = keys.iter().find(|&&(key, _)| key == "start")
+                .ok_or_else(|| format!("missing {}.start in config", section))?.1;
+            let start = start.parse().ok().filter(|s: &f32| s.is_finite() && *s >= 0.0)
+                .ok_or_else(|| format!("invalid value for {}.start in config: {}", section, start))?;
+            let keys: Vec<_> = keys.into_iter().filter(|&(key, _)| key != "start").collect();
+            let (settings, _) = PostSettings::parse(section, &keys, &default, &params)?;
+            scenes.push(PostScene { name: name.to_string(), start, settings });
+        }
+        scenes.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap());
+        if let Some(w) = scenes.windows(2).find(|w| w[0].start == w[1].start) {
+            return Err(format!("postprocess.{} and postprocess.{} start at the same time in config",
+                               w[0].name, w[1].name));
+        }
+        Ok(PostScenes { default, scenes })
+    }
+
+    /// The chain in use at time `t`.
+    pub fn at(&self, t: f32) -> &PostSettings {
+        self.scenes.iter().rev().find(|scene| scene.start <= t).map_or(&self.default, |scene| &scene.settings)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub(crate) struct Vertex {
+    position: [f32; 2],
+}
+implement_vertex!(Vertex, position);
+
+/// Covers the whole viewport, to draw with shaders/post.vert.
+pub(crate) fn quad<F: ?Sized + Facade>(display: &F) -> VertexBuffer<Vertex> {
+    let quad = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]].iter()
+        .map(|&position| Vertex { position }).collect::<Vec<_>>();
+    VertexBuffer::new(display, &quad).unwrap()
+}
+
+// Intermediate images, for one size of the scene.
+struct Buffers {
+    size: (u32, u32),
+    // The passes go back and forth between these two.
+    ping_pong: [Texture2d; 2],
+    // For the bloom, at half the size.
+    bloom: [Texture2d; 2],
+}
+
+impl Buffers {
+    fn new<F: ?Sized + Facade>(display: &F, (width, height): (u32, u32)) -> Buffers {
+        let format = render_target::hdr_format(display);
+        let texture = |width: u32, height: u32| Texture2d::empty_with_format(
+            display, format, MipmapsOption::NoMipmap,
+            width.max(1), height.max(1)).unwrap();
+        Buffers {
+            size: (width, height),
+            ping_pong: [texture(width, height), texture(width, height)],
+            bloom: [texture(width / 2, height / 2), texture(width / 2, height / 2)],
+        }
+    }
+}
+
+pub(crate) fn program<F: ?Sized + Facade>(display: &F, fragment_shader: &str) -> Program {
+    Program::from_source(display, include_str!("shaders/post.vert"), fragment_shader, None).unwrap()
+}
+
+fn sampled(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+    texture.sampled()
+        .wrap_function(SamplerWrapFunction::Clamp)
+        .minify_filter(MinifySamplerFilter::Linear)
+        .magnify_filter(MagnifySamplerFilter::Linear)
+}
+
+pub struct PostProcess {
+    settings: PostScenes,
+    quad: VertexBuffer<Vertex>,
+    bright: Program,
+    blur: Program,
+    bloom: Program,
+    vignette: Program,
+    chromatic_aberration: Program,
+    film_grain: Program,
+    color_grading: Program,
+    tonemap: Program,
+    // Allocated on first use, and again when the scene changes size.
+    buffers: RefCell<Option<Buffers>>,
+}
+
+impl PostProcess {
+    pub fn new<F: ?Sized + Facade>(display: &F, settings: &PostScenes) -> PostProcess {
+        PostProcess {
+            settings: settings.clone(),
+            quad: quad(display),
+            bright: program(display, include_str!("shaders/post_bright.frag")),
+            blur: program(display, include_str!("shaders/post_blur.frag")),
+            bloom: program(display, include_str!("shaders/post_bloom.frag")),
+            vignette: program(display, include_str!("shaders/post_vignette.frag")),
+            chromatic_aberration: program(display, include_str!("shaders/post_chromatic_aberration.frag")),
+            film_grain: program(display, include_str!("shaders/post_film_grain.frag")),
+            color_grading: program(display, include_str!("shaders/post_color_grading.frag")),
+            tonemap: program(display, include_str!("shaders/post_tonemap.frag")),
+            buffers: RefCell::new(None),
+        }
+    }
+
+    pub fn settings(&self) -> &PostScenes {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: &PostScenes) {
+        self.settings = settings.clone();
+    }
+
+    fn draw<S: Surface, U: Uniforms>(&self, surface: &mut S, program: &Program, uniforms: &U,
+                                     viewport: Option<Rect>) {
+        let params = glium::DrawParameters { viewport, ..Default::default() };
+        surface.draw(&self.quad, NoIndices(PrimitiveType::TriangleStrip), program, uniforms, &params).unwrap();
+    }
+
+    /// Run the passes of the chain at time `t` on the scene, then tonemap it
+    /// to the given area of the frame. `t` also animates the film grain.
+    pub fn apply<F, S>(&self, display: &F, scene: &Texture2d, frame: &mut S, viewport: &Rect, t: f32)
+            where F: ?Sized + Facade, S: Surface {
+        let mut buffers = self.buffers.borrow_mut();
+        let size = scene.dimensions();
+        if buffers.as_ref().map(|b| b.size) != Some(size) {
+            *buffers = Some(Buffers::new(display, size));
+        }
+        let buffers = buffers.as_ref().unwrap();
+        let settings = self.settings.at(t);
+
+        let mut src = scene;
+        for (i, pass) in settings.passes.iter().enumerate() {
+            let dst = &buffers.ping_pong[i % 2];
+            let mut target = SimpleFrameBuffer::new(display, dst).unwrap();
+            match *pass {
+                Pass::Bloom { threshold, intensity } => {
+                    let [half, tmp] = &buffers.bloom;
+                    let (width, height) = half.dimensions();
+                    // Spread the taps a bit for a wider glow.
+                    let step = 1.5;
+                    self.draw(&mut SimpleFrameBuffer::new(display, half).unwrap(), &self.bright, &uniform! {
+                        tex: sampled(src),
+                        threshold: threshold,
+                    }, None);
+                    self.draw(&mut SimpleFrameBuffer::new(display, tmp).unwrap(), &self.blur, &uniform! {
+                        tex: sampled(half),
+                        direction: [step / width as f32, 0.0],
+                    }, None);
+                    self.draw(&mut SimpleFrameBuffer::new(display, half).unwrap(), &self.blur, &uniform! {
+                        tex: sampled(tmp),
+                        direction: [0.0, step / height as f32],
+                    }, None);
+                    self.draw(&mut target, &self.bloom, &uniform! {
+                        tex: sampled(src),
+                        bloom: sampled(half),
+                        intensity: intensity,
+                    }, None);
+                }
+                Pass::Vignette { strength, radius } => self.draw(&mut target, &self.vignette, &uniform! {
+                    tex: sampled(src),
+                    strength: strength,
+                    radius: radius,
+                }, None),
+                Pass::ChromaticAberration { amount } => {
+                    self.draw(&mut target, &self.chromatic_aberration, &uniform! {
+                        tex: sampled(src),
+                        amount: amount,
+                    }, None)
+                }
+                Pass::FilmGrain { amount } => self.draw(&mut target, &self.film_grain, &uniform! {
+                    tex: sampled(src),
+                    amount: amount,
+                    t: t,
+                }, None),
+                Pass::ColorGrading { contrast, saturation, gain } => {
+                    self.draw(&mut target, &self.color_grading, &uniform! {
+                        tex: sampled(src),
+                        contrast: contrast,
+                        saturation: saturation,
+                        gain: gain,
+                    }, None)
+                }
+            }
+            src = dst;
+        }
+
+        self.draw(frame, &self.tonemap, &uniform! {
+            tex: sampled(src),
+            exposure: settings.exposure,
+            tonemap: settings.tonemap as i32,
+        }, Some(*viewport));
+    }
+}
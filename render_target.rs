@@ -0,0 +1,141 @@
+// Offscreen color + depth target the scene can be rendered into, then
+// copied to the window or post-processed (see postprocess.rs).
+
+use glium::framebuffer::{DepthRenderBuffer, DepthStencilRenderBuffer, SimpleFrameBuffer, ValidationError};
+use glium::texture::{DepthFormat, DepthStencilFormat, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::MagnifySamplerFilter;
+use glium::backend::Facade;
+use glium::{Api, BlitTarget, Rect, Surface};
+
+pub const WIDESCREEN: f32 = 16.0 / 9.0;
+
+/// The largest area with the given aspect ratio centered in a width x height
+/// window, with black bars above and below or on the sides.
+pub fn letterbox(width: u32, height: u32, aspect: f32) -> Rect {
+    let (w, h) = if width as f32 > height as f32 * aspect {
+        ((height as f32 * aspect).round() as u32, height)
+    } else {
+        (width, (width as f32 / aspect).round() as u32)
+    };
+    Rect { left: (width - w) / 2, bottom: (height - h) / 2, width: w, height: h }
+}
+
+/// The format of the HDR buffers: half floats where we can render into
+/// them, 8 bits per channel otherwise.
+///
+/// On GLES that takes 3.2 or EXT_color_buffer_float, which glium doesn't
+/// track, and glium allocates the textures there with an 8-bit pixel type
+/// that half floats don't accept. So GLES gets 8 bits, clamping the colors
+/// to 1 before the post-processing.
+pub fn hdr_format<F: ?Sized + Facade>(display: &F) -> UncompressedFloatFormat {
+    let context = display.get_context();
+    let hdr = UncompressedFloatFormat::F16F16F16F16;
+    if context.get_opengl_version().0 == Api::Gl && hdr.is_color_renderable(&**context) {
+        hdr
+    } else {
+        UncompressedFloatFormat::U8U8U8U8
+    }
+}
+
+/// Depth buffer for offscreen rendering.
+///
+/// glium only allows depth-only render buffers on desktop GL, on GLES we
+/// take a packed depth + stencil one (OES_packed_depth_stencil) instead.
+pub enum DepthBuffer {
+    Depth(DepthRenderBuffer),
+    DepthStencil(DepthStencilRenderBuffer),
+}
+
+impl DepthBuffer {
+    pub fn new<F: ?Sized + Facade>(display: &F, width: u32, height: u32) -> Result<DepthBuffer, String> {
+        if DepthFormat::I24.is_supported(&**display.get_context()) {
+            DepthRenderBuffer::new(display, DepthFormat::I24, width, height).map(DepthBuffer::Depth)
+        } else {
+            DepthStencilRenderBuffer::new(display, DepthStencilFormat::I24I8, width, height)
+                .map(DepthBuffer::DepthStencil)
+        }.map_err(|err| format!("{:?}", err))
+    }
+
+    /// A framebuffer drawing into `color` with this depth buffer.
+    pub fn framebuffer<'a, F: ?Sized + Facade>(&'a self, display: &F, color: &'a Texture2d)
+            -> Result<SimpleFrameBuffer<'a>, ValidationError> {
+        match self {
+            DepthBuffer::Depth(depth) => SimpleFrameBuffer::with_depth_buffer(display, color, depth),
+            DepthBuffer::DepthStencil(depth) => SimpleFrameBuffer::with_depth_stencil_buffer(display, color, depth),
+        }
+    }
+}
+
+pub struct RenderTarget {
+    format: UncompressedFloatFormat,
+    color: Texture2d,
+    depth: DepthBuffer,
+}
+
+impl RenderTarget {
+    pub fn new<F: ?Sized + Facade>(display: &F, width: u32, height: u32) -> RenderTarget {
+        RenderTarget::try_new(display, width, height).unwrap()
+    }
+
+    /// Fails when the size exceeds what the GL implementation supports.
+    pub fn try_new<F: ?Sized + Facade>(display: &F, width: u32, height: u32) -> Result<RenderTarget, String> {
+        RenderTarget::try_with_format(display, UncompressedFloatFormat::U8U8U8U8, width, height)
+    }
+
+    /// With half floats, for colors brighter than white until they're
+    /// tonemapped, where the GL implementation allows (see `hdr_format`).
+    pub fn new_hdr<F: ?Sized + Facade>(display: &F, width: u32, height: u32) -> RenderTarget {
+        RenderTarget::try_new_hdr(display, width, height).unwrap()
+    }
+
+    pub fn try_new_hdr<F: ?Sized + Facade>(display: &F, width: u32, height: u32) -> Result<RenderTarget, String> {
+        RenderTarget::try_with_format(display, hdr_format(display), width, height)
+    }
+
+    fn try_with_format<F: ?Sized + Facade>(display: &F, format: UncompressedFloatFormat, width: u32, height: u32)
+            -> Result<RenderTarget, String> {
+        Ok(RenderTarget {
+            format,
+            color: Texture2d::empty_with_format(display, format, MipmapsOption::NoMipmap, width, height)
+                .map_err(|err| format!("{:?}", err))?,
+            depth: DepthBuffer::new(display, width, height)?,
+        })
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.color.dimensions()
+    }
+
+    /// Reallocate the buffers if the size changed, in the same format. Their
+    /// content is lost.
+    pub fn resize<F: ?Sized + Facade>(&mut self, display: &F, width: u32, height: u32) {
+        if self.dimensions() != (width, height) {
+            *self = RenderTarget::try_with_format(display, self.format, width, height).unwrap();
+        }
+    }
+
+    pub fn color(&self) -> &Texture2d {
+        &self.color
+    }
+
+    /// RGBA pixels, bottom row first.
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let image: RawImage2d<'_, u8> = self.color.read();
+        image.data.into_owned()
+    }
+
+    pub fn framebuffer<F: ?Sized + Facade>(&self, display: &F) -> SimpleFrameBuffer<'_> {
+        self.depth.framebuffer(display, &self.color).unwrap()
+    }
+
+    /// Copy the color buffer to the given area of a surface, scaling as needed.
+    pub fn blit_to<S: Surface>(&self, surface: &S, rect: &Rect) {
+        let target = BlitTarget {
+            left: rect.left,
+            bottom: rect.bottom,
+            width: rect.width as i32,
+            height: rect.height as i32,
+        };
+        self.color.as_surface().blit_whole_color_to(surface, &target, MagnifySamplerFilter::Linear);
+    }
+}
@@ -0,0 +1,137 @@
+// Retro output filter: the picture as a Commodore 64 would show it.
+//
+// The scene is rendered and post-processed at the 320x200 resolution of the
+// C64 screen, then reduced to the 16 colors of the VIC-II (text::PALETTE)
+// with ordered dithering and blown up to the window, each C64 pixel covering
+// the same whole number of window pixels (shaders/retro.frag).
+//
+// It's turned on with `retro` in the [display] section of the config file,
+// or --retro, and toggled with F8. The look can be tuned in the [retro]
+// section:
+//
+//   [retro]
+//   dither = 0.25     # Strength of the ordered dithering, 0 for none
+//   scanlines = 0     # Darkening between the lines of pixels, up to 1
+//   curvature = 0     # Bulge of the CRT glass, 0 for a flat screen
+
+use crate::config::Config;
+use crate::postprocess;
+use crate::render_target;
+use crate::text::PALETTE;
+use crate::uniform_array::{Merge, Vec4Array};
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::{NoIndices, PrimitiveType};
+use glium::texture::{MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::{Program, Rect, Surface, VertexBuffer, uniform};
+
+/// Resolution of the C64 screen.
+pub const WIDTH: u32 = 320;
+pub const HEIGHT: u32 = 200;
+
+/// The largest area of the viewport, centered, where every C64 pixel takes
+/// the same whole number of window pixels. When the viewport is smaller than
+/// the C64 screen, the largest area with its aspect ratio.
+pub fn screen_rect(viewport: &Rect) -> Rect {
+    let scale = (viewport.width / WIDTH).min(viewport.height / HEIGHT);
+    let (width, height) = if scale > 0 {
+        (WIDTH * scale, HEIGHT * scale)
+    } else {
+        let rect = render_target::letterbox(viewport.width, viewport.height, WIDTH as f32 / HEIGHT as f32);
+        (rect.width, rect.height)
+    };
+    Rect {
+        left: viewport.left + (viewport.width - width) / 2,
+        bottom: viewport.bottom + (viewport.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetroSettings {
+    pub dither: f32,
+    pub scanlines: f32,
+    pub curvature: f32,
+}
+
+impl Default for RetroSettings {
+    fn default() -> RetroSettings {
+        RetroSettings {
+            dither: 0.25,
+            scanlines: 0.0,
+            curvature: 0.0,
+        }
+    }
+}
+
+impl RetroSettings {
+    pub fn from_config(config: &Config) -> Result<RetroSettings, String> {
+        let mut settings = RetroSettings::default();
+        for (key, v) in config.section("retro") {
+            let invalid = || format!("invalid value for retro.{} in config: {}", key, v);
+            let value = || v.parse().ok().filter(|&x: &f32| x >= 0.0).ok_or_else(invalid);
+            match key {
+                "dither" => settings.dither = value()?,
+                "scanlines" => settings.scanlines = value()?.min(1.0),
+                "curvature" => settings.curvature = value()?,
+                _ => (),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+// The palette uniform array of shaders/retro.frag.
+type PaletteArray = [[f32; 4]; 16];
+
+pub struct RetroFilter {
+    settings: RetroSettings,
+    // The picture at the C64 resolution, before its colors are reduced.
+    screen: Texture2d,
+    quad: VertexBuffer<postprocess::Vertex>,
+    program: Program,
+    palette: PaletteArray,
+}
+
+impl RetroFilter {
+    pub fn new<F: ?Sized + Facade>(display: &F, settings: &RetroSettings) -> RetroFilter {
+        let mut palette = [[0.0; 4]; 16];
+        for (dst, c) in palette.iter_mut().zip(PALETTE.iter()) {
+            *dst = [c[0] as f32 / 255.0, c[1] as f32 / 255.0, c[2] as f32 / 255.0, 1.0];
+        }
+        RetroFilter {
+            settings: settings.clone(),
+            screen: Texture2d::empty_with_format(
+                display, UncompressedFloatFormat::U8U8U8U8, MipmapsOption::NoMipmap, WIDTH, HEIGHT).unwrap(),
+            quad: postprocess::quad(display),
+            program: postprocess::program(display, include_str!("shaders/retro.frag")),
+            palette,
+        }
+    }
+
+    pub fn settings(&self) -> &RetroSettings {
+        &self.settings
+    }
+
+    /// Where to draw the picture, WIDTH x HEIGHT pixels in the window's colors.
+    pub fn framebuffer<F: ?Sized + Facade>(&self, display: &F) -> SimpleFrameBuffer<'_> {
+        SimpleFrameBuffer::new(display, &self.screen).unwrap()
+    }
+
+    /// Show the picture in the viewport, which should come from screen_rect()
+    /// for square pixels.
+    pub fn draw<S: Surface>(&self, frame: &mut S, viewport: &Rect) {
+        let uniforms = Merge(uniform! {
+            tex: &self.screen,
+            origin: [viewport.left as f32, viewport.bottom as f32],
+            size: [viewport.width as f32, viewport.height as f32],
+            dither: self.settings.dither,
+            scanlines: self.settings.scanlines,
+            curvature: self.settings.curvature,
+        }, Vec4Array::new("palette", &self.palette));
+        let params = glium::DrawParameters { viewport: Some(*viewport), ..Default::default() };
+        frame.draw(&self.quad, NoIndices(PrimitiveType::TriangleStrip), &self.program, &uniforms, &params)
+            .unwrap();
+    }
+}
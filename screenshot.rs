@@ -1,13 +1,282 @@
-#[cfg(feature = "image")]
-pub fn take_screenshot(display : &glium::Display) {
-    let image: glium::texture::RawImage2d<'_, u8> = display.read_front_buffer().unwrap();
-    let image = image::ImageBuffer::from_raw(image.width, image.height, image.data.into_owned()).unwrap();
-    let image = image::DynamicImage::ImageRgba8(image).flipv().to_rgb();
-    let image = image::DynamicImage::ImageRgb8(image);
-    let mut output = std::fs::File::create(&std::path::Path::new("screenshot.png")).unwrap();
-    image.write_to(&mut output, image::ImageFormat::PNG).unwrap();
-}
-
-#[cfg(not(feature = "image"))]
-pub fn take_screenshot(_ : &glium::Display) {
+// Screenshots, saved as PNG with a timestamped name.
+//
+// Settings come from the [screenshot] section of the config file:
+//
+//   [screenshot]
+//   dir = ~/Pictures/mandelwow
+//   scale = 2         # Output resolution, in multiples of the window size
+//   supersample = 2   # Rendered at scale * supersample, then scaled down
+//   alpha = false     # Keep a transparent background
+//
+// The scene parameters are stored in tEXt chunks, so a screenshot can be
+// loaded back to get to the same view (see SceneInfo).
+
+use crate::config::Config;
+use crate::options::parse_bool;
+use crate::support::vec3::Vec3;
+use cgmath::Quaternion;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScreenshotSettings {
+    pub dir: PathBuf,
+    pub scale: u32,
+    pub supersample: u32,
+    pub alpha: bool,
+}
+
+impl Default for ScreenshotSettings {
+    fn default() -> ScreenshotSettings {
+        ScreenshotSettings {
+            dir: PathBuf::from("."),
+            scale: 1,
+            supersample: 1,
+            alpha: false,
+        }
+    }
+}
+
+impl ScreenshotSettings {
+    pub fn from_config(config: &Config) -> Result<ScreenshotSettings, String> {
+        let mut settings = ScreenshotSettings::default();
+        for (key, v) in config.section("screenshot") {
+            let invalid = || format!("invalid value for screenshot.{} in config: {}", key, v);
+            match key {
+                "dir" => {
+                    settings.dir = match v.strip_prefix("~/") {
+                        Some(rest) => match std::env::var_os("HOME") {
+                            Some(home) => Path::new(&home).join(rest),
+                            None => PathBuf::from(v),
+                        },
+                        None => PathBuf::from(v),
+                    }
+                }
+                "scale" | "supersample" => {
+                    let n: u32 = v.parse().map_err(|_| invalid())?;
+                    if !(1..=8).contains(&n) {
+                        return Err(invalid());
+                    }
+                    if key == "scale" { settings.scale = n } else { settings.supersample = n }
+                }
+                "alpha" => settings.alpha = parse_bool(v).ok_or_else(invalid)?,
+                _ => (),
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Size of the offscreen image to render for a window of the given size.
+    pub fn render_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let factor = self.scale * self.supersample;
+        (width * factor, height * factor)
+    }
+}
+
+/// Everything needed to render a frame again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneInfo {
+    pub t: f32,
+    /// Time of the last drum hit, which scales the fractal.
+    pub hit_time: f32,
+    pub wow: f32,
+    pub pos: Vec3,
+    pub orientation: Quaternion<f32>,
+    /// Vertical field of view, in radians.
+    pub fov: f32,
+    pub formula: String,
+    pub max_iter: u32,
+    pub slices: u32,
+    pub version: String,
+}
+
+fn join(v: &[f32]) -> String {
+    v.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+impl SceneInfo {
+    /// PNG tEXt keyword/value pairs.
+    pub fn to_text(&self) -> Vec<(String, String)> {
+        let q = self.orientation;
+        let text = [
+            ("Software", format!("MandelWow {}", self.version)),
+            ("mandelwow.t", self.t.to_string()),
+            ("mandelwow.hit_time", self.hit_time.to_string()),
+            ("mandelwow.wow", self.wow.to_string()),
+            ("mandelwow.camera.pos", join(&[self.pos.0, self.pos.1, self.pos.2])),
+            ("mandelwow.camera.orientation", join(&[q.s, q.v.x, q.v.y, q.v.z])),
+            ("mandelwow.camera.fov", self.fov.to_string()),
+            ("mandelwow.formula", self.formula.clone()),
+            ("mandelwow.max_iter", self.max_iter.to_string()),
+            ("mandelwow.slices", self.slices.to_string()),
+        ];
+        text.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    pub fn from_text(text: &[(String, String)]) -> Result<SceneInfo, String> {
+        let get = |key: &str| {
+            text.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+                .ok_or_else(|| format!("missing {}", key))
+        };
+        fn floats(key: &str, v: &str, n: usize) -> Result<Vec<f32>, String> {
+            let v: Vec<f32> = v.split_whitespace().map(|c| c.parse().ok()).collect::<Option<_>>()
+                .ok_or_else(|| format!("invalid {}: {}", key, v))?;
+            if v.len() != n {
+                return Err(format!("invalid {}: expected {} numbers", key, n));
+            }
+            Ok(v)
+        }
+        fn number<T: std::str::FromStr>(key: &str, v: &str) -> Result<T, String> {
+            v.parse().map_err(|_| format!("invalid {}: {}", key, v))
+        }
+        fn time(key: &str, v: &str) -> Result<f32, String> {
+            number(key, v).ok().filter(|t: &f32| t.is_finite()).ok_or_else(|| format!("invalid {}: {}", key, v))
+        }
+        let pos = floats("camera position", get("mandelwow.camera.pos")?, 3)?;
+        let q = floats("camera orientation", get("mandelwow.camera.orientation")?, 4)?;
+        Ok(SceneInfo {
+            t: time("t", get("mandelwow.t")?)?,
+            // Not saved by older versions.
+            hit_time: get("mandelwow.hit_time").map_or(Ok(0.0), |v| time("hit_time", v))?,
+            wow: number("wow", get("mandelwow.wow")?)?,
+            pos: Vec3(pos[0], pos[1], pos[2]),
+            orientation: Quaternion::new(q[0], q[1], q[2], q[3]),
+            fov: number("fov", get("mandelwow.camera.fov")?)?,
+            formula: get("mandelwow.formula")?.to_string(),
+            max_iter: number("max_iter", get("mandelwow.max_iter")?)?,
+            slices: number("slices", get("mandelwow.slices")?)?,
+            version: get("Software")?.trim_start_matches("MandelWow ").to_string(),
+        })
+    }
+}
+
+// Days since 1970-01-01 to (year, month, day), from Howard Hinnant's date algorithms.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+/// File name for a screenshot taken at the given time (UTC),
+/// e.g. mandelwow-20170605-213012-345.png
+pub fn timestamped_name(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as i64;
+    let (y, m, d) = civil_from_days(secs.div_euclid(86_400));
+    let s = secs.rem_euclid(86_400);
+    format!("mandelwow-{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}.png",
+            y, m, d, s / 3600, s / 60 % 60, s % 60, since_epoch.subsec_millis())
+}
+
+// Don't overwrite older screenshots taken within the same millisecond.
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let stem = name.trim_end_matches(".png");
+    (1..).map(|i| dir.join(format!("{}-{}.png", stem, i))).find(|p| !p.exists()).unwrap()
+}
+
+/// Box-filter RGBA pixels down by an integer factor.
+pub fn downsample(pixels: &[u8], width: u32, height: u32, factor: u32) -> Vec<u8> {
+    if factor == 1 {
+        return pixels.to_vec();
+    }
+    let (w, h, f) = (width as usize, height as usize, factor as usize);
+    let (out_w, out_h) = (w / f, h / f);
+    let mut out = vec![0u8; out_w * out_h * 4];
+    for y in 0..out_h {
+        for x in 0..out_w {
+            let mut sum = [0u32; 4];
+            for sy in y * f..(y + 1) * f {
+                for sx in x * f..(x + 1) * f {
+                    let p = (sy * w + sx) * 4;
+                    for (c, s) in sum.iter_mut().enumerate() {
+                        *s += pixels[p + c] as u32;
+                    }
+                }
+            }
+            let o = (y * out_w + x) * 4;
+            for (c, s) in sum.iter().enumerate() {
+                out[o + c] = ((s + (f * f / 2) as u32) / (f * f) as u32) as u8;
+            }
+        }
+    }
+    out
+}
+
+/// Save RGBA pixels, bottom row first as read back from OpenGL, with a new
+/// timestamped name in the configured directory. Returns the file name.
+pub fn take_screenshot(pixels: &[u8], width: u32, height: u32, settings: &ScreenshotSettings,
+                       info: &SceneInfo) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(&settings.dir)?;
+    let path = unique_path(&settings.dir, &timestamped_name(SystemTime::now()));
+    write_png(&path, pixels, width, height, settings.alpha, &info.to_text())?;
+    Ok(path)
+}
+
+/// Read back the scene parameters of a screenshot.
+pub fn load_scene_info(path: &Path) -> io::Result<SceneInfo> {
+    let text = read_png_text(path)?;
+    SceneInfo::from_text(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(feature = "png")]
+fn write_png(path: &Path, pixels: &[u8], width: u32, height: u32, alpha: bool,
+             text: &[(String, String)]) -> io::Result<()> {
+    let other = |err: png::EncodingError| io::Error::other(err.to_string());
+    let file = io::BufWriter::new(std::fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(if alpha { png::ColorType::Rgba } else { png::ColorType::Rgb });
+    encoder.set_depth(png::BitDepth::Eight);
+    for (keyword, value) in text {
+        encoder.add_text_chunk(keyword.clone(), value.clone()).map_err(other)?;
+    }
+    let mut writer = encoder.write_header().map_err(other)?;
+
+    // PNG rows go top to bottom.
+    let mut data = Vec::with_capacity(pixels.len());
+    for row in pixels.chunks_exact(width as usize * 4).rev() {
+        if alpha {
+            data.extend_from_slice(row);
+        } else {
+            row.chunks_exact(4).for_each(|p| data.extend_from_slice(&p[..3]));
+        }
+    }
+    writer.write_image_data(&data).map_err(other)?;
+    writer.finish().map_err(other)
+}
+
+#[cfg(feature = "png")]
+fn read_png_text(path: &Path) -> io::Result<Vec<(String, String)>> {
+    let other = |err: png::DecodingError| io::Error::new(io::ErrorKind::InvalidData, err.to_string());
+    let decoder = png::Decoder::new(io::BufReader::new(std::fs::File::open(path)?));
+    let mut reader = decoder.read_info().map_err(other)?;
+    // Text chunks may also follow the image data.
+    let mut buf = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut buf).map_err(other)?;
+    reader.finish().map_err(other)?;
+    let info = reader.info();
+    Ok(info.uncompressed_latin1_text.iter().map(|c| (c.keyword.clone(), c.text.clone())).collect())
+}
+
+#[cfg(not(feature = "png"))]
+fn write_png(_: &Path, _: &[u8], _: u32, _: u32, _: bool, _: &[(String, String)]) -> io::Result<()> {
+    Err(io::Error::other("built without the png feature"))
+}
+
+#[cfg(not(feature = "png"))]
+fn read_png_text(_: &Path) -> io::Result<Vec<(String, String)>> {
+    Err(io::Error::other("built without the png feature"))
 }
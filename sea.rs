@@ -0,0 +1,311 @@
+// A sea of cubes with a character on top of each one, which bob up and down
+// on waves and can scroll by, wrapping around at the edges.
+//
+// The whole sea is drawn with one instanced call for the cubes and one for
+// the characters. The cubes are placed and the waves computed in the vertex
+// shaders (shaders/sea_*.vert) from the time and the grid coordinates.
+//
+// It can be set in the [sea] section of the config file:
+//
+//   [sea]
+//   size = 40x25          # Columns x rows of cubes, up to 1024x1024
+//   spacing = 1           # Distance between the centers of two cubes
+//   origin = -20 -2       # x, z of the first cube; rows extend towards -z
+//   y = -2.5              # Height of the sea at rest
+//   scroll = 0 0          # Units per second along x and z
+//   # Waves, replacing the default ones: amplitude  frequency  direction  speed
+//   wave1 = 0.3  0.39  0   2
+//   wave2 = 0.3  0.38  90  3
+//
+// The frequency is in radians per grid cell, the direction in degrees
+// (0 is along the x axis, 90 along the z axis, away from the camera) and the
+// speed in radians per second. The waves stay in place while the cubes
+// scroll through them.
+
+use crate::config::Config;
+use crate::lights::Lights;
+use crate::shaded_cube::{self, ShadedCube};
+use crate::shadow::ShadowMap;
+use crate::text::Text;
+use crate::uniform_array::{Merge, Vec4Array};
+use cgmath::conv::array4x4;
+use cgmath::{Deg, Matrix4, Vector3};
+use glium::backend::Facade;
+use glium::{Program, Surface, VertexBuffer, implement_vertex, uniform};
+use std::f32::consts::PI;
+
+/// Waves beyond this are rejected: the shaders have a fixed size array.
+pub const MAX_WAVES: usize = 8;
+
+/// Columns and rows beyond this are rejected, a million cubes is plenty.
+pub const MAX_SIZE: u32 = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wave {
+    pub amplitude: f32,
+    /// Radians per grid cell.
+    pub frequency: f32,
+    /// Degrees from the x axis.
+    pub direction: f32,
+    /// Radians per second.
+    pub speed: f32,
+}
+
+impl Wave {
+    // Direction times frequency.
+    fn wave_vector(&self) -> (f32, f32) {
+        let (sin, cos) = self.direction.to_radians().sin_cos();
+        (cos * self.frequency, sin * self.frequency)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeaSettings {
+    pub columns: u32,
+    pub rows: u32,
+    pub spacing: f32,
+    /// x and z of the first cube.
+    pub origin: (f32, f32),
+    pub y: f32,
+    /// Units per second along x and z.
+    pub scroll: (f32, f32),
+    pub waves: Vec<Wave>,
+}
+
+impl Default for SeaSettings {
+    fn default() -> SeaSettings {
+        SeaSettings {
+            columns: 40,
+            rows: 25,
+            spacing: 1.0,
+            origin: (-20.0, -2.0),
+            y: -2.5,
+            scroll: (0.0, 0.0),
+            waves: vec![
+                Wave { amplitude: 0.3, frequency: PI * 5.0 / 40.0, direction: 0.0, speed: 2.0 },
+                Wave { amplitude: 0.3, frequency: PI * 3.0 / 25.0, direction: 90.0, speed: 3.0 },
+            ],
+        }
+    }
+}
+
+impl SeaSettings {
+    pub fn from_config(config: &Config) -> Result<SeaSettings, String> {
+        let mut settings = SeaSettings::default();
+        let mut waves = Vec::new();
+        for (key, v) in config.section("sea") {
+            let invalid = || format!("invalid value for sea.{} in config: {}", key, v);
+            let pair = || -> Result<(f32, f32), String> {
+                let p: Vec<f32> = v.split_whitespace().map(|c| c.parse().ok()).collect::<Option<_>>()
+                    .ok_or_else(invalid)?;
+                if p.len() != 2 || !p.iter().all(|c| c.is_finite()) {
+                    return Err(invalid());
+                }
+                Ok((p[0], p[1]))
+            };
+            match key {
+                "size" => {
+                    let (columns, rows) = v.split_once('x')
+                        .and_then(|(c, r)| Some((c.trim().parse().ok()?, r.trim().parse().ok()?)))
+                        .filter(|&(c, r): &(u32, u32)| c > 0 && r > 0 && c <= MAX_SIZE && r <= MAX_SIZE)
+                        .ok_or_else(invalid)?;
+                    settings.columns = columns;
+                    settings.rows = rows;
+                }
+                "spacing" => {
+                    settings.spacing = v.parse().ok().filter(|&s: &f32| s.is_finite() && s > 0.0).ok_or_else(invalid)?;
+                }
+                "origin" => settings.origin = pair()?,
+                "y" => settings.y = v.parse().ok().filter(|y: &f32| y.is_finite()).ok_or_else(invalid)?,
+                "scroll" => settings.scroll = pair()?,
+                _ => (),
+            }
+            if let Some(n) = key.strip_prefix("wave") {
+                let n: usize = n.parse().map_err(|_| format!("unknown key sea.{} in config", key))?;
+                if !(1..=MAX_WAVES).contains(&n) {
+                    return Err(format!("sea.{}: at most {} waves are supported", key, MAX_WAVES));
+                }
+                let p: Vec<f32> = v.split_whitespace().map(|c| c.parse().ok()).collect::<Option<_>>()
+                    .ok_or_else(invalid)?;
+                if p.len() != 4 || p[0] < 0.0 || !p.iter().all(|c| c.is_finite()) {
+                    return Err(invalid());
+                }
+                if waves.iter().any(|&(m, _)| m == n) {
+                    return Err(format!("sea.{}: wave {} is set twice in config", key, n));
+                }
+                waves.push((n, Wave { amplitude: p[0], frequency: p[1], direction: p[2], speed: p[3] }));
+            }
+        }
+        if !waves.is_empty() {
+            waves.sort_by_key(|&(n, _)| n);
+            settings.waves = waves.into_iter().map(|(_, wave)| wave).collect();
+        }
+        Ok(settings)
+    }
+
+    /// Height of the cube at grid coordinates (x, z) at time t, as computed by
+    /// the shaders.
+    pub fn height(&self, x: f32, z: f32, t: f32) -> f32 {
+        self.waves.iter().map(|w| {
+            let (kx, kz) = w.wave_vector();
+            w.amplitude * (kx * x + kz * z + w.speed * t).sin()
+        }).sum()
+    }
+
+    /// Center of the cube at grid coordinates (x, z) at time t, as computed
+    /// by the shaders.
+    pub fn position(&self, x: f32, z: f32, t: f32) -> Vector3<f32> {
+        let size = (self.columns as f32, self.rows as f32);
+        let x = (x + self.scroll.0 / self.spacing * t).rem_euclid(size.0);
+        let z = (z - self.scroll.1 / self.spacing * t).rem_euclid(size.1);
+        Vector3::new(self.origin.0 + x * self.spacing, self.y + self.height(x, z, t),
+                     self.origin.1 - z * self.spacing)
+    }
+
+    /// Opposite corners of a box containing the cubes at any time.
+    pub fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let amplitude: f32 = self.waves.iter().map(|w| w.amplitude).sum();
+        let width = self.columns as f32 * self.spacing;
+        let depth = self.rows as f32 * self.spacing;
+        // The cubes are 1 unit wide around their center.
+        (Vector3::new(self.origin.0 - 0.5, self.y - amplitude - 0.5, self.origin.1 - depth - 0.5),
+         Vector3::new(self.origin.0 + width + 0.5, self.y + amplitude + 0.5, self.origin.1 + 0.5))
+    }
+
+    fn uniform_array(&self) -> WavesArray {
+        let mut waves = [[0.0; 4]; MAX_WAVES];
+        for (dst, w) in waves.iter_mut().zip(&self.waves) {
+            let (kx, kz) = w.wave_vector();
+            *dst = [kx, kz, w.amplitude, w.speed];
+        }
+        waves
+    }
+}
+
+// The waves array of shaders/sea_waves.glsl.
+type WavesArray = [[f32; 4]; MAX_WAVES];
+
+// Per-instance attributes of a cube of the sea, and of the character on top of it.
+#[derive(Clone, Copy, Debug)]
+struct SeaCell {
+    // Grid coordinates, before scrolling.
+    cell: [f32; 2],
+    // Character to draw on top.
+    index: i32,
+}
+implement_vertex!(SeaCell, cell, index);
+
+fn sea_program<F: ?Sized + Facade>(display: &F, vertex_shader: &str, fragment_shader: &str) -> Program {
+    let vertex_shader_src = [include_str!("shaders/sea_waves.glsl"), vertex_shader].concat();
+    Program::from_source(display, &vertex_shader_src, fragment_shader, None).unwrap()
+}
+
+fn cube_program<F: ?Sized + Facade>(display: &F) -> Program {
+    sea_program(display, include_str!("shaders/sea_cube.vert"), &shaded_cube::fragment_shader())
+}
+
+fn shadow_program<F: ?Sized + Facade>(display: &F) -> Program {
+    let fragment_shader_src = [include_str!("shaders/shadow.frag"), include_str!("shaders/pack_depth.glsl")].concat();
+    sea_program(display, include_str!("shaders/sea_cube.vert"), &fragment_shader_src)
+}
+
+fn glyph_program<F: ?Sized + Facade>(display: &F) -> Program {
+    sea_program(display, include_str!("shaders/sea_text.vert"), include_str!("shaders/text.frag"))
+}
+
+pub struct Sea {
+    settings: SeaSettings,
+    glyph_offset: usize,
+    cube: ShadedCube,
+    text: Text,
+    cells: VertexBuffer<SeaCell>,
+    cube_program: Program,
+    glyph_program: Program,
+    shadow_program: Program,
+    waves: WavesArray,
+}
+
+impl Sea {
+    /// `glyph_offset` is the character shown on the first cube, the next ones follow.
+    pub fn new<F: ?Sized + Facade>(display: &F, settings: &SeaSettings, glyph_offset: usize) -> Sea {
+        Sea {
+            settings: settings.clone(),
+            glyph_offset,
+            cube: ShadedCube::new(display),
+            text: Text::new(display),
+            cells: Sea::cells(display, settings, glyph_offset),
+            cube_program: cube_program(display),
+            glyph_program: glyph_program(display),
+            shadow_program: shadow_program(display),
+            waves: settings.uniform_array(),
+        }
+    }
+
+    fn cells<F: ?Sized + Facade>(display: &F, settings: &SeaSettings, glyph_offset: usize)
+            -> VertexBuffer<SeaCell> {
+        let (columns, rows) = (settings.columns as usize, settings.rows as usize);
+        let mut cells = Vec::with_capacity(columns.checked_mul(rows).expect("sea grid too large"));
+        for x in 0..columns {
+            for z in 0..rows {
+                cells.push(SeaCell {
+                    cell: [x as f32, z as f32],
+                    index: (x + z * columns).wrapping_add(glyph_offset) as u8 as i32,
+                });
+            }
+        }
+        VertexBuffer::new(display, &cells).unwrap()
+    }
+
+    pub fn settings(&self) -> &SeaSettings {
+        &self.settings
+    }
+
+    /// Change the grid and the waves.
+    pub fn set_settings<F: ?Sized + Facade>(&mut self, display: &F, settings: &SeaSettings) {
+        if (settings.columns, settings.rows) != (self.settings.columns, self.settings.rows) {
+            self.cells = Sea::cells(display, settings, self.glyph_offset);
+        }
+        self.waves = settings.uniform_array();
+        self.settings = settings.clone();
+    }
+
+    /// `eye` is the camera position, for the highlights.
+    pub fn draw<S: Surface>(&self, frame: &mut S, perspview: &[[f32; 4]; 4], eye: [f32; 3], t: f32,
+                            lights: &Lights, shadow: Option<&ShadowMap>) {
+        let s = &self.settings;
+        let glyph = Matrix4::from_translation(Vector3::new(0.0, 0.501, 0.0)) * Matrix4::from_angle_x(Deg(-90.0f32));
+        // Cells per second.
+        let scroll = [s.scroll.0 / s.spacing, -s.scroll.1 / s.spacing];
+        self.cube.draw_instanced(frame, &self.cube_program, &self.cells, Merge(uniform! {
+            perspview: *perspview,
+            eye: eye,
+            t: t,
+            grid_size: [s.columns as f32, s.rows as f32],
+            origin: [s.origin.0, s.y, s.origin.1],
+            spacing: s.spacing,
+            scroll: scroll,
+        }, Vec4Array::new("waves", &self.waves)), lights, shadow);
+        self.text.draw_instanced(frame, &self.glyph_program, &self.cells, Merge(uniform! {
+            perspview: *perspview,
+            glyph: array4x4(glyph),
+            t: t,
+            grid_size: [s.columns as f32, s.rows as f32],
+            origin: [s.origin.0, s.y, s.origin.1],
+            spacing: s.spacing,
+            scroll: scroll,
+        }, Vec4Array::new("waves", &self.waves)));
+    }
+
+    /// Draw the cubes into the shadow map, from its light.
+    pub fn draw_shadow<S: Surface>(&self, frame: &mut S, shadow: &ShadowMap, t: f32) {
+        let s = &self.settings;
+        self.cube.draw_shadow_with(frame, &self.shadow_program, &self.cells, &Merge(uniform! {
+            perspview: shadow.perspview(),
+            t: t,
+            grid_size: [s.columns as f32, s.rows as f32],
+            origin: [s.origin.0, s.y, s.origin.1],
+            spacing: s.spacing,
+            scroll: [s.scroll.0 / s.spacing, -s.scroll.1 / s.spacing],
+        }, Vec4Array::new("waves", &self.waves)));
+    }
+}
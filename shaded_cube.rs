@@ -1,12 +1,21 @@
+use crate::lights::Lights;
+use crate::shadow::ShadowMap;
+use crate::uniform_array::{Merge, Vec4Array};
 use glium;
-use glium::{Display, Program, Surface, implement_vertex};
+use glium::backend::Facade;
+use glium::{Program, Surface, implement_vertex, uniform};
 use glium::index::{IndexBuffer, PrimitiveType};
-use std::rc::Rc;
+use glium::uniforms::{AsUniformValue, Uniforms, UniformsStorage};
 
-pub fn shaded_program(display: &Display) -> Program {
+/// Source of the fragment shader lighting the cubes, for programs with
+/// their own vertex shader.
+pub fn fragment_shader() -> String {
+    [include_str!("shaders/lights.glsl"), include_str!("shaders/shaded.frag")].concat()
+}
+
+pub fn shaded_program<F: ?Sized + Facade>(display: &F) -> Program {
     let vertex_shader_src = include_str!("shaders/shaded.vert");
-    let fragment_shader_src = include_str!("shaders/shaded.frag");
-    Program::from_source(display, vertex_shader_src, fragment_shader_src, None).unwrap()
+    Program::from_source(display, vertex_shader_src, &fragment_shader(), None).unwrap()
 }
 
 #[derive(Copy, Clone)]
@@ -18,52 +27,55 @@ implement_vertex!(Vertex, position, normal);
 
 pub struct ShadedCube {
     vertexes: glium::VertexBuffer<Vertex>,
-    program: Rc<Program>,
     indices: IndexBuffer<u16>,
+    // For drawing without shadows.
+    no_shadow: ShadowMap,
 }
 
 impl<'a> ShadedCube {
-    pub fn new(display: &Display, program: Rc<Program>) -> ShadedCube {
-        //      x--->
-        //      4 ──────┐ 5
-        //      ╱┆     ╱│
-        //   0 ┌─────┐1 │
-        // y   │ 7+┄┄│┄┄+ 6    z
-        // |   │╱    │ ╱    ╱
-        // v   └─────┘
-        //    3       2
-        let vertex_data = [
-            // Front
-            Vertex { position: [-0.5, -0.5,  0.5], normal: [  0.,  0., -1.] },  // 0
-            Vertex { position: [ 0.5, -0.5,  0.5], normal: [ -1.,  0.,  0.] },  // 1
-            Vertex { position: [ 0.5,  0.5,  0.5], normal: [  0., -1.,  0.] },  // 2
-            Vertex { position: [-0.5,  0.5,  0.5], normal: [  1.,  0.,  0.] },  // 3
-
-            // Back
-            Vertex { position: [-0.5, -0.5, -0.5], normal: [  0.,  1.,  0.] },  // 4
-            Vertex { position: [ 0.5, -0.5, -0.5], normal: [  0.,  0.,  1.] },  // 5
-            Vertex { position: [ 0.5,  0.5, -0.5], normal: [  0.,  0.,  0.] },  // 6
-            Vertex { position: [-0.5,  0.5, -0.5], normal: [  0.,  0.,  0.] },  // 7
+    pub fn new<F: ?Sized + Facade>(display: &F) -> ShadedCube {
+        // Each face has its own 4 vertices, so they all carry the face normal.
+        // Corners go counter-clockwise seen from outside the cube.
+        //
+        //        y
+        //        ^   7 ┌─────┐ 6
+        //        │    ╱┆    ╱│
+        //        │ 3 ┌─────┐2│
+        //        │   │4└┄┄┄│┄┘ 5
+        //        │   │╱    │╱
+        //        │ 0 └─────┘ 1
+        //        └──────────> x
+        //       ╱
+        //      z
+        const P: [[f32; 3]; 8] = [
+            [-0.5, -0.5,  0.5], [ 0.5, -0.5,  0.5], [ 0.5,  0.5,  0.5], [-0.5,  0.5,  0.5],
+            [-0.5, -0.5, -0.5], [ 0.5, -0.5, -0.5], [ 0.5,  0.5, -0.5], [-0.5,  0.5, -0.5],
         ];
-        const INDICES: &[u16] = &[
-             1,  2,  0,  2,  3,  0,    // Front
-             5,  6,  1,  6,  2,  1,    // Right
-             6,  7,  2,  7,  3,  2,    // Top
-             4,  0,  3,  7,  4,  3,    // Left
-             5,  1,  4,  1,  0,  4,    // Top
-             7,  6,  5,  4,  7,  5u16  // Back
+        const FACES: [([f32; 3], [usize; 4]); 6] = [
+            ([ 0.,  0.,  1.], [0, 1, 2, 3]),  // Front
+            ([ 1.,  0.,  0.], [1, 5, 6, 2]),  // Right
+            ([ 0.,  1.,  0.], [3, 2, 6, 7]),  // Top
+            ([-1.,  0.,  0.], [4, 0, 3, 7]),  // Left
+            ([ 0., -1.,  0.], [4, 5, 1, 0]),  // Bottom
+            ([ 0.,  0., -1.], [5, 4, 7, 6]),  // Back
         ];
+        let mut vertex_data = Vec::with_capacity(24);
+        let mut indices = Vec::with_capacity(36);
+        for (normal, corners) in FACES.iter() {
+            let first = vertex_data.len() as u16;
+            vertex_data.extend(corners.iter().map(|&c| Vertex { position: P[c], normal: *normal }));
+            indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
 
         ShadedCube {
             vertexes: glium::VertexBuffer::new(display, &vertex_data).unwrap(),
-            program: program,
-            indices:  IndexBuffer::new(display, PrimitiveType::TrianglesList, INDICES).unwrap(),
+            indices:  IndexBuffer::new(display, PrimitiveType::TrianglesList, &indices).unwrap(),
+            no_shadow: ShadowMap::new(display, 1),
         }
     }
 
-    pub fn draw<U>(&self, frame: &mut glium::Frame, uniforms: &U)
-            where U: glium::uniforms::Uniforms {
-        let params = glium::DrawParameters {
+    fn params() -> glium::DrawParameters<'a> {
+        glium::DrawParameters {
             depth: glium::Depth {
                 test: glium::draw_parameters::DepthTest::IfLess,
                 write: true,
@@ -71,7 +83,53 @@ impl<'a> ShadedCube {
             },
             backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise,
             ..Default::default()
-        };
-        frame.draw(&self.vertexes, &self.indices, &self.program, uniforms, &params).unwrap();
+        }
+    }
+
+    // Front faces are left out of the shadow map: cube surfaces facing the light
+    // are then well in front of the depth it records, and don't shadow themselves.
+    fn shadow_params() -> glium::DrawParameters<'a> {
+        glium::DrawParameters {
+            backface_culling: glium::draw_parameters::BackfaceCullingMode::CullCounterClockwise,
+            ..Self::params()
+        }
+    }
+
+    /// Draw one cube with shaded_program() or the like. The uniforms need
+    /// model, col, perspview and the eye position.
+    pub fn draw<S, T, R>(&self, frame: &mut S, program: &Program, uniforms: UniformsStorage<'_, T, R>,
+                         lights: &Lights, shadow: Option<&ShadowMap>)
+            where S: Surface, T: AsUniformValue, R: Uniforms {
+        let lights = lights.uniform_array();
+        let shadow = shadow.unwrap_or(&self.no_shadow);
+        let uniforms = Merge(uniforms
+            .add("shadow_map", shadow.sampled())
+            .add("shadow_matrix", shadow.matrix()), Vec4Array::new("lights", &lights));
+        frame.draw(&self.vertexes, &self.indices, program, &uniforms, &Self::params()).unwrap();
+    }
+
+    /// Draw one cube per instance in a single call, with a program taking
+    /// the per-instance attributes and the fragment_shader().
+    pub fn draw_instanced<S, I, U>(&self, frame: &mut S, program: &Program,
+                                   instances: &glium::VertexBuffer<I>, uniforms: U, lights: &Lights,
+                                   shadow: Option<&ShadowMap>)
+            where S: Surface, I: Copy, U: Uniforms {
+        let lights = lights.uniform_array();
+        let shadow = shadow.unwrap_or(&self.no_shadow);
+        let uniforms = Merge(Merge(uniforms, uniform! {
+            shadow_map: shadow.sampled(),
+            shadow_matrix: shadow.matrix(),
+        }), Vec4Array::new("lights", &lights));
+        frame.draw((&self.vertexes, instances.per_instance().unwrap()), &self.indices,
+                   program, &uniforms, &Self::params()).unwrap();
+    }
+
+    /// Draw the instances into a shadow map, with a depth-only program
+    /// (shaders/shadow.frag) and perspview looking from the light.
+    pub fn draw_shadow_with<S, I, U>(&self, frame: &mut S, program: &Program,
+                                     instances: &glium::VertexBuffer<I>, uniforms: &U)
+            where S: Surface, I: Copy, U: Uniforms {
+        frame.draw((&self.vertexes, instances.per_instance().unwrap()), &self.indices,
+                   program, uniforms, &Self::shadow_params()).unwrap();
     }
 }
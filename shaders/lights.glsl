@@ -0,0 +1,73 @@
+#version 300 es
+precision highp float;
+
+// Prepended to the shaders doing lighting (see lights.rs).
+
+const int MAX_LIGHTS = 4;
+
+// [0]: ambient color, shininess
+// [1]: specular strength, number of lights, index of the light casting
+//      shadows or -1
+// Then two for each light:
+//   position and w = 1, or direction towards the light and w = 0
+//   color, attenuation
+uniform vec4 lights[2 + 2 * MAX_LIGHTS];
+
+// Camera position, for the highlights.
+uniform vec3 eye;
+
+// Depth of the shadow casters seen from the main light (see shadow.rs).
+uniform highp sampler2D shadow_map;
+// From world space to shadow map coordinates and depth.
+uniform mat4 shadow_matrix;
+
+// Inverse of pack_depth() in pack_depth.glsl.
+float unpack_depth(vec4 c) {
+    return dot(c.rgb, vec3(1.0, 1.0 / 255.0, 1.0 / 65025.0));
+}
+
+// How much of the main light gets through, 0 in the shadows.
+float lit(vec3 position, vec3 n) {
+    // Start a bit off the surface, so it doesn't shadow itself.
+    vec4 p = shadow_matrix * vec4(position + n * 0.05, 1.0);
+    vec3 q = p.xyz / p.w;
+    if (any(greaterThan(abs(q - 0.5), vec3(0.5)))) {
+        return 1.0;  // Out of the map
+    }
+    // Soften the edges.
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+    float sum = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            sum += step(q.z, unpack_depth(texture(shadow_map, q.xy + vec2(x, y) * texel)));
+        }
+    }
+    return sum / 9.0;
+}
+
+// Blinn-Phong shading of a point in world space.
+vec3 shade(vec3 position, vec3 normal, vec3 col) {
+    vec3 n = normalize(normal);
+    vec3 v = normalize(eye - position);
+    vec3 color = lights[0].rgb * col;
+    int count = int(lights[1].y);
+    for (int i = 0; i < MAX_LIGHTS; i++) {
+        if (i >= count) {
+            break;
+        }
+        vec4 p = lights[2 + 2 * i];
+        vec4 c = lights[3 + 2 * i];
+        vec3 l = p.xyz - position * p.w;
+        float d = length(l);
+        l /= d;
+        // Directional lights have no attenuation.
+        float attenuation = 1. / (1. + c.w * d * d);
+        if (i == int(lights[1].z)) {
+            attenuation *= lit(position, n);
+        }
+        float diffuse = max(dot(n, l), 0.);
+        float specular = diffuse > 0. ? pow(max(dot(n, normalize(l + v)), 0.), lights[0].w) : 0.;
+        color += (col * diffuse + lights[1].x * specular) * c.rgb * attenuation;
+    }
+    return color;
+}
@@ -0,0 +1,25 @@
+#version 300 es
+precision highp float;
+
+// Color of the fractal at c with z0 = z. Shared by the fractal and its
+// shadow, which only keeps the opaque enough parts.
+vec4 mandelwow_color(vec2 c, vec2 z) {
+    float zx = z.x;
+    float zy = z.y;
+    const int maxiter = 64;
+    for (int iter = maxiter; iter > 0; iter--) {
+        float zx2 = zx * zx;
+        float zy2 = zy * zy;
+        if (zx2 * zy2 > 4.0) {
+          float index = float(iter) / float(maxiter);
+          // Float targets don't clamp: a negative alpha would blow up the blending.
+          return vec4(index, 0.1, 1.0 - index / 2.0, max(0.8 - index * index, 0.0));
+        }
+        zy = zx * zy * 2.0 + c.y;
+        zx = zx2 - zy2 + c.x;
+    }
+    return vec4((sin(z.y) + 1.0) / 4.0,
+                (sin(z.x) + 1.0) / 4.0,
+                (sin(c.x) + 1.0) / 4.0,
+                1.0);
+}
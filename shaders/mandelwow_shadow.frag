@@ -0,0 +1,13 @@
+in vec2 c;
+in vec2 z;
+out vec4 depth;
+
+vec4 pack_depth(float depth);  // From pack_depth.glsl, appended
+
+void main() {
+    // Mostly transparent areas don't cast shadows.
+    if (mandelwow_color(c, z).a < 0.5) {
+        discard;
+    }
+    depth = pack_depth(gl_FragCoord.z);
+}
@@ -0,0 +1,9 @@
+
+// Depth in [0, 1] spread over the RGB channels of an 8-bit texture, which
+// every GL can render to (see shadow.rs). Alpha is 1, so that blending
+// leaves it alone. unpack_depth() is in lights.glsl.
+vec4 pack_depth(float depth) {
+    vec3 enc = fract(depth * vec3(1.0, 255.0, 65025.0));
+    enc -= enc.yzz * vec3(1.0 / 255.0, 1.0 / 255.0, 0.0);
+    return vec4(enc, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 300 es
+precision highp float;
+
+// Bloom, last step: add the blurred highlights to the picture.
+in vec2 v_uv;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main() {
+    vec4 c = texture(tex, v_uv);
+    color = vec4(c.rgb + texture(bloom, v_uv).rgb * intensity, c.a);
+}
@@ -0,0 +1,19 @@
+#version 300 es
+precision highp float;
+
+// Bloom, 2nd step: gaussian blur, once horizontally and once vertically.
+in vec2 v_uv;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform vec2 direction;  // Between two taps
+
+void main() {
+    const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+    vec3 sum = texture(tex, v_uv).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        sum += texture(tex, v_uv + direction * float(i)).rgb * weights[i];
+        sum += texture(tex, v_uv - direction * float(i)).rgb * weights[i];
+    }
+    color = vec4(sum, 1.);
+}
@@ -0,0 +1,15 @@
+#version 300 es
+precision highp float;
+
+// Bloom, 1st step: keep what's brighter than the threshold, at half size.
+in vec2 v_uv;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform float threshold;
+
+void main() {
+    vec3 c = texture(tex, v_uv).rgb;
+    float brightness = max(c.r, max(c.g, c.b));
+    color = vec4(c * max(brightness - threshold, 0.) / max(brightness, 1e-4), 1.);
+}
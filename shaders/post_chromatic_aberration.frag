@@ -0,0 +1,16 @@
+#version 300 es
+precision highp float;
+
+// Split red and blue apart, more and more away from the center like a
+// cheap lens.
+in vec2 v_uv;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform float amount;  // Offset at the corners, in fractions of the width
+
+void main() {
+    vec2 offset = (v_uv - 0.5) * 2. * amount;
+    vec4 c = texture(tex, v_uv);
+    color = vec4(texture(tex, v_uv + offset).r, c.g, texture(tex, v_uv - offset).b, c.a);
+}
@@ -0,0 +1,19 @@
+#version 300 es
+precision highp float;
+
+// Contrast around the middle grey, saturation and a tint.
+in vec2 v_uv;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform float contrast;
+uniform float saturation;
+uniform vec3 gain;
+
+void main() {
+    vec4 c = texture(tex, v_uv);
+    vec3 graded = (c.rgb - 0.5) * contrast + 0.5;
+    float luma = dot(graded, vec3(0.2126, 0.7152, 0.0722));
+    graded = mix(vec3(luma), graded, saturation) * gain;
+    color = vec4(max(graded, 0.), c.a);
+}
@@ -0,0 +1,20 @@
+#version 300 es
+precision highp float;
+
+// Noise changing on every frame.
+in vec2 v_uv;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform float amount;
+uniform float t;
+
+float random(vec2 p) {
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+void main() {
+    vec4 c = texture(tex, v_uv);
+    float noise = random(gl_FragCoord.xy + fract(t * 7.) * vec2(97., 13.)) - 0.5;
+    color = vec4(max(c.rgb + noise * amount, 0.), c.a);
+}
@@ -0,0 +1,26 @@
+#version 300 es
+precision highp float;
+
+// From HDR to what the window can show. Always the last pass.
+in vec2 v_uv;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform float exposure;
+uniform int tonemap;  // 0: clamp, 1: Reinhard, 2: ACES
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+vec3 aces(vec3 x) {
+    return (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+}
+
+void main() {
+    vec4 c = texture(tex, v_uv);
+    vec3 x = c.rgb * exposure;
+    if (tonemap == 1) {
+        x = x / (1. + x);
+    } else if (tonemap == 2) {
+        x = aces(x);
+    }
+    color = vec4(clamp(x, 0., 1.), c.a);
+}
@@ -0,0 +1,16 @@
+#version 300 es
+precision highp float;
+
+// Darken the corners.
+in vec2 v_uv;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform float strength;
+uniform float radius;  // Where it starts, 1 is the middle of the edges
+
+void main() {
+    vec4 c = texture(tex, v_uv);
+    float d = length(v_uv - 0.5) * 2.;
+    color = vec4(c.rgb * (1. - strength * smoothstep(radius, 1.5, d)), c.a);
+}
@@ -0,0 +1,58 @@
+#version 300 es
+precision highp float;
+
+// The C64 screen blown up to the viewport, in the colors of the VIC-II
+// (see retro.rs).
+out vec4 color;
+
+uniform sampler2D tex;
+// Viewport in the window, in pixels.
+uniform vec2 origin;
+uniform vec2 size;
+uniform float dither;
+uniform float scanlines;
+uniform float curvature;
+
+uniform vec4 palette[16];  // sRGB
+
+const float PI = 3.14159265;
+
+// Ordered dithering thresholds, in sixteenths.
+const float bayer[16] = float[16](
+     0.,  8.,  2., 10.,
+    12.,  4., 14.,  6.,
+     3., 11.,  1.,  9.,
+    15.,  7., 13.,  5.);
+
+void main() {
+    vec2 uv = (gl_FragCoord.xy - origin) / size;
+    // Bulge like the glass of a CRT, with black corners.
+    vec2 p = uv * 2. - 1.;
+    uv = p * (1. + curvature * dot(p, p)) * 0.5 + 0.5;
+    if (any(lessThan(uv, vec2(0.))) || any(greaterThan(uv, vec2(1.)))) {
+        color = vec4(0., 0., 0., 1.);
+        return;
+    }
+
+    ivec2 screen = textureSize(tex, 0);
+    ivec2 pixel = min(ivec2(uv * vec2(screen)), screen - 1);
+    // Colors are compared in sRGB, like the palette.
+    vec3 c = pow(texelFetch(tex, pixel, 0).rgb, vec3(1. / 2.2));
+    c += ((bayer[pixel.y % 4 * 4 + pixel.x % 4] + 0.5) / 16. - 0.5) * dither;
+
+    vec3 nearest = palette[0].rgb;
+    float best = 1e9;
+    for (int i = 0; i < 16; i++) {
+        vec3 d = c - palette[i].rgb;
+        float distance = dot(d, d);
+        if (distance < best) {
+            best = distance;
+            nearest = palette[i].rgb;
+        }
+    }
+
+    // Darker between the lines of pixels.
+    float line = fract(uv.y * float(screen.y));
+    float scanline = 1. - scanlines * (1. - sin(line * PI));
+    color = vec4(pow(nearest, vec3(2.2)) * scanline, 1.);
+}
@@ -0,0 +1,22 @@
+
+in vec3 position;
+in vec3 normal;
+out vec3 v_position;
+out vec3 v_normal;
+flat out vec3 v_col;
+
+// Per instance
+in vec2 cell;
+
+uniform mat4 perspview;
+
+void main() {
+    float wave;
+    vec3 offset = sea_position(cell, wave);
+    v_col = vec3(0., abs(1. - wave) * 0.5, abs(wave));
+
+    // The model is a pure translation: normals are unchanged.
+    v_position = position + offset;
+    v_normal = normal;
+    gl_Position = perspview * vec4(v_position, 1.0);
+}
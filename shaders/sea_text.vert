@@ -0,0 +1,24 @@
+
+in vec2 position;
+in vec2 tex_coords;
+
+// Per instance
+in vec2 cell;
+in int index;
+
+uniform mat4 perspview;
+// Places the character on top of the cube.
+uniform mat4 glyph;
+
+out vec2 v_tex_coords;
+
+void main() {
+    float wave;
+    vec3 offset = sea_position(cell, wave);
+    gl_Position = perspview * (glyph * vec4(position, 0.0, 1.0) + vec4(offset, 0.0));
+
+    // Characters are arranged in a 16x16 square.
+    int xpos = index % 16;
+    int ypos = index / 16;
+    v_tex_coords = (tex_coords + vec2(xpos, ypos)) / 16.;
+}
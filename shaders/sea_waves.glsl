@@ -0,0 +1,34 @@
+#version 300 es
+precision highp float;
+
+// Shared by the sea shaders, which are appended to this file (see sea.rs).
+
+const int MAX_WAVES = 8;
+
+// Wave vector (direction times frequency), amplitude, speed.
+// Unused waves have zero amplitude.
+uniform vec4 waves[MAX_WAVES];
+
+uniform float t;
+
+// The grid: columns x rows cubes, rows extending towards -z from the first one.
+uniform vec2 grid_size;
+uniform vec3 origin;
+uniform float spacing;
+uniform vec2 scroll;  // Cells per second
+
+float wave_height(vec2 cell) {
+    float h = 0.0;
+    for (int i = 0; i < MAX_WAVES; i++) {
+        vec4 w = waves[i];
+        h += w.z * sin(dot(w.xy, cell) + w.w * t);
+    }
+    return h;
+}
+
+// Position of the center of a cube, wrapping around the grid as it scrolls.
+vec3 sea_position(vec2 cell, out float wave) {
+    vec2 c = mod(cell + scroll * t, grid_size);
+    wave = wave_height(c);
+    return origin + vec3(c.x * spacing, wave, -c.y * spacing);
+}
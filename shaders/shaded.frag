@@ -1,9 +1,9 @@
-#version 300 es
-precision lowp float;
 
-flat in vec4 color;
+in vec3 v_position;  // World space
+in vec3 v_normal;
+flat in vec3 v_col;
 out vec4 color_out;
 
 void main() {
-    color_out = color;
+    color_out = vec4(shade(v_position, v_normal, v_col), 1.0);
 }
@@ -0,0 +1,11 @@
+#version 300 es
+precision highp float;
+
+// Depth of the shadow casters, for the shadow map.
+out vec4 depth;
+
+vec4 pack_depth(float depth);  // From pack_depth.glsl, appended
+
+void main() {
+    depth = pack_depth(gl_FragCoord.z);
+}
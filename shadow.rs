@@ -0,0 +1,98 @@
+// Shadows cast by the main light, the first directional one (see lights.rs).
+//
+// Each frame the shadow casters are drawn from the light, with an
+// orthographic projection covering the whole scene, and their depth is
+// recorded in a texture. The shaded program (shaders/lights.glsl) then
+// compares the depth of each fragment with it to find whether something
+// stands in between.
+//
+// The depth is packed in an RGBA8 texture (shaders/pack_depth.glsl) rather
+// than a depth or float texture, for portability: GL ES 2.0 can't sample
+// depth textures without OES_depth_texture, nor render to float textures
+// without EXT_color_buffer_float, while RGBA8 targets work everywhere. The
+// depth test itself uses a render buffer (see render_target::DepthBuffer).
+
+use crate::render_target::DepthBuffer;
+use cgmath::conv::array4x4;
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3, ortho};
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction};
+use glium::Surface;
+
+/// View and orthographic projection of a directional light shining from
+/// `direction` on a sphere: the sphere fits in the clip volume, the side
+/// facing the light at the near plane.
+pub fn light_matrices(direction: Vector3<f32>, center: Point3<f32>, radius: f32)
+        -> (Matrix4<f32>, Matrix4<f32>) {
+    let direction = direction.normalize();
+    let up = if direction.y.abs() > 0.99 { Vector3::unit_z() } else { Vector3::unit_y() };
+    let view = Matrix4::look_at_rh(center + direction * radius, center, up);
+    let projection = ortho(-radius, radius, -radius, radius, 0.0, 2.0 * radius);
+    (view, projection)
+}
+
+pub struct ShadowMap {
+    packed_depth: Texture2d,
+    depth: DepthBuffer,
+    view: Matrix4<f32>,
+    projection: Matrix4<f32>,
+}
+
+impl ShadowMap {
+    /// An empty map, where nothing is in the shadow.
+    pub fn new<F: ?Sized + Facade>(display: &F, size: u32) -> ShadowMap {
+        let shadow = ShadowMap {
+            packed_depth: Texture2d::empty_with_format(
+                display, UncompressedFloatFormat::U8U8U8U8, MipmapsOption::NoMipmap, size, size).unwrap(),
+            depth: DepthBuffer::new(display, size, size).unwrap(),
+            view: Matrix4::from_scale(1.0),
+            projection: Matrix4::from_scale(1.0),
+        };
+        // Empty until the first shadow casters are drawn.
+        shadow.framebuffer(display);
+        shadow
+    }
+
+    /// Aim at a sphere containing everything casting or receiving shadows.
+    pub fn set_light(&mut self, direction: [f32; 3], center: [f32; 3], radius: f32) {
+        let (view, projection) = light_matrices(direction.into(), center.into(), radius);
+        self.view = view;
+        self.projection = projection;
+    }
+
+    pub fn view(&self) -> [[f32; 4]; 4] {
+        array4x4(self.view)
+    }
+
+    pub fn projection(&self) -> [[f32; 4]; 4] {
+        array4x4(self.projection)
+    }
+
+    pub fn perspview(&self) -> [[f32; 4]; 4] {
+        array4x4(self.projection * self.view)
+    }
+
+    /// From world space to texture coordinates and depth in the map.
+    pub fn matrix(&self) -> [[f32; 4]; 4] {
+        let bias = Matrix4::from_translation(Vector3::new(0.5, 0.5, 0.5)) * Matrix4::from_scale(0.5);
+        array4x4(bias * self.projection * self.view)
+    }
+
+    /// Where to draw the shadow casters, from the light, with a program
+    /// writing their packed depth. It starts empty, nothing in the shadow.
+    pub fn framebuffer<F: ?Sized + Facade>(&self, display: &F) -> SimpleFrameBuffer<'_> {
+        let mut framebuffer = self.depth.framebuffer(display, &self.packed_depth).unwrap();
+        // Unpacks to beyond the far plane.
+        framebuffer.clear_color_and_depth((1.0, 1.0, 1.0, 1.0), 1.0);
+        framebuffer
+    }
+
+    pub fn sampled(&self) -> Sampler<'_, Texture2d> {
+        self.packed_depth.sampled()
+            .wrap_function(SamplerWrapFunction::Clamp)
+            .magnify_filter(MagnifySamplerFilter::Nearest)
+            .minify_filter(MinifySamplerFilter::Nearest)
+    }
+}
@@ -9,62 +9,148 @@ const SAMPLE_RATE: i32 = 48000;
 
 struct XmCallback {
     xm: XMContext,
+    mute: bool,
 }
 
 impl AudioCallback for XmCallback {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut [f32]) {
+        // Keep the module running even when muted: it drives the hit events.
         self.xm.generate_samples(out);
+        if self.mute {
+            for sample in out.iter_mut() {
+                *sample = 0.0;
+            }
+        }
     }
 }
 
+pub struct PlayOptions<'a> {
+    pub filename: &'a str,
+    pub device: Option<&'a str>,
+    pub mute: bool,
+    pub start_time: f32,
+    pub looping: bool,
+}
+
 pub struct SoundPlayer {
     device: Option<AudioDevice<XmCallback>>,
+    looping: bool,
 }
 
-fn play_xm(raw_xm: &[u8]) -> SoundPlayer {
-    let sdl_context = sdl2::init().unwrap();
-    let sdl_audio = sdl_context.audio().unwrap();
+// Sets up the module for an audio device running at freq.
+fn xm_callback(raw_xm: &[u8], options: &PlayOptions<'_>, freq: i32) -> XmCallback {
+    let mut xm = XMContext::new(raw_xm, freq as u32).unwrap();
+    if !options.looping {
+        xm.set_max_loop_count(1);
+    }
+
+    // Fast-forward to the start time by rendering and discarding samples,
+    // at most up to the end of the module.
+    let mut skip = ((options.start_time * freq as f32) as usize).saturating_mul(2);
+    let mut scratch = vec![0f32; 8192];
+    while skip > 0 && xm.loop_count() == 0 {
+        let n = skip.min(scratch.len());
+        xm.generate_samples(&mut scratch[..n]);
+        skip -= n;
+    }
+
+    XmCallback {
+        xm: xm,
+        mute: options.mute,
+    }
+}
+
+fn play_xm(sdl_context: &sdl2::Sdl, raw_xm: &[u8], options: &PlayOptions<'_>) -> SoundPlayer {
+    let no_sound = SoundPlayer { device: None, looping: options.looping };
+    let sdl_audio = match sdl_context.audio() {
+        Ok(sdl_audio) => sdl_audio,
+        Err(err) => {
+            println!("Couldn't initialize audio: {}", err);
+            return no_sound;
+        }
+    };
 
     let desired_spec = AudioSpecDesired {
         freq: Some(SAMPLE_RATE),
         channels: Some(2),
         samples: Some(4096),  // 85ms
     };
-    let device = sdl_audio.open_playback(None, &desired_spec, |actual_spec| {
-        let xm = XMContext::new(raw_xm, actual_spec.freq as u32).unwrap();
-
-        XmCallback {
-            xm: xm,
+    let open = |device| sdl_audio.open_playback(device, &desired_spec, |actual_spec| {
+        xm_callback(raw_xm, options, actual_spec.freq)
+    });
+    let device = open(options.device).or_else(|err| match options.device {
+        Some(name) => {
+            println!("Couldn't open audio device {}: {}", name, err);
+            open(None)
         }
-    }).unwrap();
+        None => Err(err),
+    });
+    let device = match device {
+        Ok(device) => device,
+        Err(err) => {
+            println!("Couldn't open the default audio device: {}", err);
+            return no_sound;
+        }
+    };
 
     device.resume();
 
     SoundPlayer {
         device: Some(device),
+        looping: options.looping,
     }
 }
 
-pub fn start() -> SoundPlayer {
-    let filename = "flora.xm";
+pub fn start(sdl_context: &sdl2::Sdl, options: &PlayOptions<'_>) -> SoundPlayer {
+    let filename = options.filename;
     match File::open(filename) {
         Result::Ok(mut f) => {
             let mut xm = Vec::new();
             f.read_to_end(&mut xm).unwrap();
-            return play_xm(&xm);
+            return play_xm(sdl_context, &xm, options);
         },
         Result::Err(err) => {
             println!("Couldn't open module {}: {:?}", filename, err);
         },
     }
-    SoundPlayer { device: None }
+    SoundPlayer { device: None, looping: options.looping }
+}
+
+/// Row rate of an XM module: each row lasts `speed` ticks and there are
+/// `bpm * 2 / 5` ticks per second.
+pub fn rows_per_second(bpm: u16, speed: u16) -> f32 {
+    f32::from(bpm) * 2.0 / 5.0 / f32::from(speed.max(1))
+}
+
+/// Current (bpm, speed) of the playing module, if any.
+pub fn playing_speed(player: &mut SoundPlayer) -> Option<(u16, u16)> {
+    let device = player.device.as_mut()?;
+    let speed = device.lock().xm.playing_speed();
+    Some((speed.bpm, speed.tempo))
+}
+
+/// Whether the demo is over, given how many times the module wrapped around.
+/// libxm counts the loops even when it's allowed to play forever.
+pub fn has_ended(looping: bool, loop_count: u8) -> bool {
+    !looping && loop_count > 0
+}
+
+/// True once a module started without looping has played to the end.
+pub fn finished(player: &mut SoundPlayer) -> bool {
+    match player.device.as_mut() {
+        Some(device) => has_ended(player.looping, device.lock().xm.loop_count()),
+        None => false,
+    }
 }
 
 pub fn hit_event(player: &mut SoundPlayer) -> f32 {
     use std::ops::Deref;
-    let audio_device_lock = player.device.as_mut().unwrap().lock();
+    let audio_device_lock = match player.device.as_mut() {
+        Some(device) => device.lock(),
+        None => return 0.0,
+    };
     let xm_callback = audio_device_lock.deref();
     let xm = &xm_callback.xm;
     let n_samples = xm.latest_trigger_of_instrument(0x1D);
@@ -1,17 +1,201 @@
-use cgmath::{Matrix4, Vector4};
+use cgmath::{InnerSpace, Matrix4, Quaternion, Rad, Rotation, Rotation3, Vector3, Vector4};
 use cgmath::conv::array4x4;
 use glium;
-use glium::glutin::event::{ ElementState, VirtualKeyCode, WindowEvent };
+use glium::glutin::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use crate::config::Config;
+use crate::keymap::Action;
 use std::f32;
 use std::f32::consts::PI;
 use crate::support::vec3::Vec3;
-use crate::support::vec3::norm;
 
-#[derive(Default)]
+/// Camera motion parameters. Speeds are in units (or radians) per second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraSpeed {
+    pub walk: f32,
+    pub strafe: f32,
+    /// Yaw rate when turning with keys or the gamepad.
+    pub turn: f32,
+    pub pitch: f32,
+    pub roll: f32,
+    /// Radians per pixel of mouse motion.
+    pub mouse: f32,
+    /// Radians per pixel of mouse drag in orbit mode.
+    pub orbit: f32,
+    /// How quickly the camera reaches full speed (1/s).
+    pub acceleration: f32,
+    /// How quickly it stops once released (1/s).
+    pub damping: f32,
+    /// How quickly the field of view follows zoom requests (1/s).
+    pub zoom: f32,
+}
+
+impl Default for CameraSpeed {
+    fn default() -> CameraSpeed {
+        CameraSpeed {
+            walk: 0.6,
+            strafe: 1.2,
+            turn: 0.5,
+            pitch: 0.12,
+            roll: 1.0,
+            mouse: 0.001,
+            orbit: 0.005,
+            acceleration: 8.0,
+            damping: 4.0,
+            zoom: 6.0,
+        }
+    }
+}
+
+impl CameraSpeed {
+    /// Defaults overridden by the [camera] section of the config file.
+    pub fn from_config(config: &Config) -> Result<CameraSpeed, String> {
+        let mut speed = CameraSpeed::default();
+        for (key, v) in config.section("camera") {
+            let field = match key {
+                "walk_speed" => &mut speed.walk,
+                "strafe_speed" => &mut speed.strafe,
+                "turn_speed" => &mut speed.turn,
+                "pitch_speed" => &mut speed.pitch,
+                "roll_speed" => &mut speed.roll,
+                "mouse_sensitivity" => &mut speed.mouse,
+                "orbit_sensitivity" => &mut speed.orbit,
+                "acceleration" => &mut speed.acceleration,
+                "damping" => &mut speed.damping,
+                "zoom_speed" => &mut speed.zoom,
+                _ => continue,
+            };
+            *field = v.parse()
+                .map_err(|_| format!("invalid value for camera.{} in config: {}", key, v))?;
+        }
+        Ok(speed)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective,
+    /// Parallel projection for technical views. The visible height matches
+    /// what the perspective view shows at the focus distance.
+    Orthographic,
+}
+
+/// Which way the view-space z axis points: `Left` looks down +z (the
+/// original MandelWow convention), `Right` down -z like gluPerspective.
+/// Both render the same image; only the intermediate view matrix differs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Handedness {
+    Left,
+    Right,
+}
+
+/// Projection parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lens {
+    /// Vertical field of view, in radians.
+    pub fov: f32,
+    pub znear: f32,
+    pub zfar: f32,
+    pub projection: Projection,
+    pub handedness: Handedness,
+}
+
+impl Default for Lens {
+    fn default() -> Lens {
+        Lens {
+            fov: PI / 2.0,
+            znear: 0.1,
+            zfar: 1024.0,
+            projection: Projection::Perspective,
+            handedness: Handedness::Left,
+        }
+    }
+}
+
+impl Lens {
+    /// Defaults overridden by the [camera] section of the config file.
+    /// The field of view is given in degrees.
+    pub fn from_config(config: &Config) -> Result<Lens, String> {
+        let mut lens = Lens::default();
+        for (key, v) in config.section("camera") {
+            let invalid = || format!("invalid value for camera.{} in config: {}", key, v);
+            match key {
+                "fov" => {
+                    let fov: f32 = v.parse().map_err(|_| invalid())?;
+                    if !(1.0..=170.0).contains(&fov) {
+                        return Err(invalid());
+                    }
+                    lens.fov = fov.to_radians();
+                }
+                "znear" => lens.znear = v.parse().map_err(|_| invalid())?,
+                "zfar" => lens.zfar = v.parse().map_err(|_| invalid())?,
+                "projection" => {
+                    lens.projection = match v {
+                        "perspective" => Projection::Perspective,
+                        "orthographic" => Projection::Orthographic,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "handedness" => {
+                    lens.handedness = match v {
+                        "left" => Handedness::Left,
+                        "right" => Handedness::Right,
+                        _ => return Err(invalid()),
+                    }
+                }
+                _ => (),
+            }
+        }
+        if !(lens.znear > 0.0 && lens.zfar > lens.znear) {
+            return Err(format!("invalid camera clip planes in config: {} {}", lens.znear, lens.zfar));
+        }
+        Ok(lens)
+    }
+}
+
+const MIN_FOV: f32 = PI / 180.0;
+const MAX_FOV: f32 = PI * 170.0 / 180.0;
+// Field of view change per press of the zoom keys.
+const ZOOM_STEP: f32 = 1.25;
+
+fn to_vector(v: Vec3) -> Vector3<f32> {
+    Vector3::new(v.0, v.1, v.2)
+}
+
+fn to_vec3(v: Vector3<f32>) -> Vec3 {
+    Vec3(v.x, v.y, v.z)
+}
+
+// The camera looks down -Z in its local frame, with +Y up.
+const FORWARD: Vector3<f32> = Vector3 { x: 0.0, y: 0.0, z: -1.0 };
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    /// Fly around with keys, mouse and gamepad.
+    Free,
+    /// Circle around a target: drag with the left button to rotate, with the
+    /// right or middle button to pan, scroll to zoom.
+    Orbit,
+}
+
+const MIN_ORBIT_DISTANCE: f32 = 0.5;
+const MAX_ORBIT_DISTANCE: f32 = 200.0;
+// Pitch is limited just short of the poles so the view doesn't flip over.
+const MAX_ORBIT_PITCH: f32 = PI / 2.0 - 0.01;
+
+/// Free-look camera. The orientation is a quaternion, so it can look straight
+/// up or down and roll without degenerating.
 pub struct CameraState {
+    mode: CameraMode,
     aspect: f32,
+    lens: Lens,
+    // Field of view the lens is easing towards, and whether the camera
+    // moves along to keep the focus point the same size (dolly zoom).
+    target_fov: f32,
+    dolly: bool,
     pos: Vec3,
-    dir: Vec3,
+    orientation: Quaternion<f32>,
+    velocity: Vec3,
+    pub speed: CameraSpeed,
 
     moving_up: bool,
     moving_left: bool,
@@ -23,25 +207,114 @@ pub struct CameraState {
     turning_left: bool,
     turning_down: bool,
     turning_right: bool,
+    rolling_left: bool,
+    rolling_right: bool,
 
+    // While the pointer is grabbed, look around with raw mouse motion.
+    mouse_grabbed: bool,
     mouse_x: i32,
     mouse_y: i32,
-    rel_x: i32,
-    rel_y: i32,
+    rel_x: f32,
+    rel_y: f32,
+
+    // Analog input from a gamepad: (strafe, up, forward) and (yaw, pitch) in -1..1.
+    analog_move: Vec3,
+    analog_look: (f32, f32),
+
+    // Orbit mode state. Yaw and pitch follow the same conventions as the free camera.
+    orbit_target: Vec3,
+    orbit_distance: f32,
+    orbit_yaw: f32,
+    orbit_pitch: f32,
+    rotating: bool,
+    panning: bool,
+    pan_x: f32,
+    pan_y: f32,
+    zoom: f32,
+}
+
+impl Default for CameraState {
+    fn default() -> CameraState {
+        CameraState::new()
+    }
 }
 
 impl CameraState {
     pub fn new() -> CameraState {
         CameraState {
+            mode: CameraMode::Free,
             aspect: 1280.0 / 720.0,
+            lens: Lens::default(),
+            target_fov: PI / 2.0,
+            dolly: false,
             pos: Vec3(0.0, 0.0, 0.0),
-            dir: Vec3(0.0, 0.0, -1.0),
+            orientation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            velocity: Vec3::default(),
+            speed: CameraSpeed::default(),
+            moving_up: false,
+            moving_left: false,
+            moving_down: false,
+            moving_right: false,
+            moving_forward: false,
+            moving_backward: false,
+            turning_up: false,
+            turning_left: false,
+            turning_down: false,
+            turning_right: false,
+            rolling_left: false,
+            rolling_right: false,
+            mouse_grabbed: false,
             mouse_x: -1,
             mouse_y: -1,
-            .. Default::default()
+            rel_x: 0.0,
+            rel_y: 0.0,
+            analog_move: Vec3::default(),
+            analog_look: (0.0, 0.0),
+            orbit_target: Vec3(0.0, 0.0, 0.0),
+            orbit_distance: 3.0,
+            orbit_yaw: 0.0,
+            orbit_pitch: 0.0,
+            rotating: false,
+            panning: false,
+            pan_x: 0.0,
+            pan_y: 0.0,
+            zoom: 0.0,
         }
     }
 
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switching to orbit mode keeps the current position and turns to face the target.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == self.mode {
+            return;
+        }
+        if mode == CameraMode::Orbit {
+            let offset = to_vector(self.orbit_target) - to_vector(self.pos);
+            let d = if offset.magnitude() > 1e-3 {
+                offset.normalize()
+            } else {
+                self.orientation.rotate_vector(FORWARD)
+            };
+            self.orbit_distance = offset.magnitude().clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
+            self.orbit_yaw = d.x.atan2(-d.z);
+            self.orbit_pitch = (-d.y.clamp(-1.0, 1.0).asin()).clamp(-MAX_ORBIT_PITCH, MAX_ORBIT_PITCH);
+            self.update_orbit(0.0);
+        }
+        self.velocity = Vec3::default();
+        self.mode = mode;
+    }
+
+    pub fn set_orbit_target(&mut self, target: Vec3) {
+        self.orbit_target = target;
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
+
     pub fn set_pos(&mut self, pos: Vec3) {
         self.pos = pos;
     }
@@ -50,50 +323,160 @@ impl CameraState {
         self.pos
     }
 
-    pub fn set_dir(&mut self, dir: Vec3) {
-        self.dir = dir;
+    /// Replace the projection parameters, stopping any zoom in progress.
+    pub fn set_lens(&mut self, lens: Lens) {
+        self.lens = lens;
+        self.target_fov = lens.fov;
     }
 
-    pub fn get_persp_mat(&self) -> Matrix4<f32> {
-        let fov: f32 = PI / 2.0;
-        let zfar = 1024.0;
-        let znear = 0.1;
+    pub fn lens(&self) -> Lens {
+        self.lens
+    }
 
-        let f = 1.0 / (fov / 2.0).tan();
+    /// Vertical field of view, in radians. Takes effect immediately.
+    pub fn set_fov(&mut self, fov: f32) {
+        self.lens.fov = fov.clamp(MIN_FOV, MAX_FOV);
+        self.target_fov = self.lens.fov;
+    }
 
-        // note: remember that this is column-major, so the lines of code are actually columns
-        Matrix4 {
-            x: Vector4{ x: f / self.aspect, y: 0.0, z:  0.0,                           w: 0.0 },
-            y: Vector4{ x: 0.0,             y: f,   z:  0.0,                           w: 0.0 },
-            z: Vector4{ x: 0.0,             y: 0.0, z:  (zfar+znear)/(zfar-znear),     w: 1.0 },
-            w: Vector4{ x: 0.0,             y: 0.0, z: -(2.0*zfar*znear)/(zfar-znear), w: 0.0 },
+    pub fn get_fov(&self) -> f32 {
+        self.lens.fov
+    }
+
+    /// Smoothly change the field of view.
+    pub fn zoom_to(&mut self, fov: f32) {
+        self.target_fov = fov.clamp(MIN_FOV, MAX_FOV);
+        self.dolly = false;
+    }
+
+    /// Smoothly change the field of view while moving the camera so that the
+    /// focus point keeps the same size on screen: the Vertigo effect.
+    pub fn dolly_zoom_to(&mut self, fov: f32) {
+        self.target_fov = fov.clamp(MIN_FOV, MAX_FOV);
+        self.dolly = true;
+    }
+
+    /// Distance to the point the camera is framing: the orbit target, or in free
+    /// mode the orbit target projected on the view direction.
+    pub fn focus_distance(&self) -> f32 {
+        match self.mode {
+            CameraMode::Orbit => self.orbit_distance,
+            CameraMode::Free => {
+                let offset = to_vector(self.orbit_target) - to_vector(self.pos);
+                offset.dot(self.orientation.rotate_vector(FORWARD)).max(self.lens.znear)
+            }
         }
     }
 
-    pub fn get_view_mat(&self) -> Matrix4<f32> {
-        let f = norm(&self.dir);
+    pub fn toggle_projection(&mut self) {
+        self.lens.projection = match self.lens.projection {
+            Projection::Perspective => Projection::Orthographic,
+            Projection::Orthographic => Projection::Perspective,
+        };
+    }
 
-        let up = Vec3(0.0, 1.0, 0.0);
+    /// Move to pos and face target, with no roll.
+    pub fn look_at(&mut self, pos: Vec3, target: Vec3) {
+        self.pos = pos;
+        if pos != target {
+            self.set_dir(target - pos);
+        }
+    }
+
+    /// The point the camera is looking at: the orbit center in orbit mode,
+    /// otherwise a point one unit ahead.
+    pub fn get_target(&self) -> Vec3 {
+        match self.mode {
+            CameraMode::Orbit => self.orbit_target,
+            CameraMode::Free => self.pos + self.get_dir(),
+        }
+    }
+
+    /// Look along dir, with no roll. Looking straight up or down keeps the
+    /// current heading, since dir alone doesn't say which way is up.
+    pub fn set_dir(&mut self, dir: Vec3) {
+        let d = to_vector(dir).normalize();
+        let mut h = d;
+        if h.x.abs() < 1e-6 && h.z.abs() < 1e-6 {
+            let f = self.orientation.rotate_vector(FORWARD);
+            let u = self.orientation.rotate_vector(Vector3::unit_y());
+            h = if f.x.abs() > 1e-3 || f.z.abs() > 1e-3 { f } else { -u * f.y.signum() };
+        }
+        let yaw = Rad((-h.x).atan2(-h.z));
+        let pitch = Rad(d.y.clamp(-1.0, 1.0).asin());
+        self.orientation = Quaternion::from_angle_y(yaw) * Quaternion::from_angle_x(pitch);
+    }
+
+    pub fn get_dir(&self) -> Vec3 {
+        to_vec3(self.orientation.rotate_vector(FORWARD))
+    }
+
+    pub fn set_orientation(&mut self, orientation: Quaternion<f32>) {
+        self.orientation = orientation.normalize();
+    }
+
+    pub fn get_orientation(&self) -> Quaternion<f32> {
+        self.orientation
+    }
+
+    pub fn is_mouse_grabbed(&self) -> bool {
+        self.mouse_grabbed
+    }
 
-        let s = Vec3(f.1 * up.2 - f.2 * up.1,
-                     f.2 * up.0 - f.0 * up.2,
-                     f.0 * up.1 - f.1 * up.0);
-        let sn = norm(&s);
+    /// Call after grabbing or releasing the pointer on the window.
+    pub fn set_mouse_grabbed(&mut self, grabbed: bool) {
+        self.mouse_grabbed = grabbed;
+        self.mouse_x = -1;
+        self.mouse_y = -1;
+    }
 
-        let u = (sn.1 * f.2 - sn.2 * f.1,
-                 sn.2 * f.0 - sn.0 * f.2,
-                 sn.0 * f.1 - sn.1 * f.0);
+    // +1 when view space looks down +z, -1 when it looks down -z.
+    fn z_sign(&self) -> f32 {
+        match self.lens.handedness {
+            Handedness::Left => 1.0,
+            Handedness::Right => -1.0,
+        }
+    }
 
-        let p = (-self.pos.0 * s.0 - self.pos.1 * s.1 - self.pos.2 * s.2,
-                 -self.pos.0 * u.0 - self.pos.1 * u.1 - self.pos.2 * u.2,
-                 -self.pos.0 * f.0 - self.pos.1 * f.1 - self.pos.2 * f.2);
+    pub fn get_persp_mat(&self) -> Matrix4<f32> {
+        let Lens { fov, znear, zfar, .. } = self.lens;
+        let h = self.z_sign();
+
+        // note: remember that this is column-major, so the lines of code are actually columns
+        match self.lens.projection {
+            Projection::Perspective => {
+                let f = 1.0 / (fov / 2.0).tan();
+                Matrix4 {
+                    x: Vector4{ x: f / self.aspect, y: 0.0, z:  0.0,                           w: 0.0 },
+                    y: Vector4{ x: 0.0,             y: f,   z:  0.0,                           w: 0.0 },
+                    z: Vector4{ x: 0.0,             y: 0.0, z:  h*(zfar+znear)/(zfar-znear),   w: h },
+                    w: Vector4{ x: 0.0,             y: 0.0, z: -(2.0*zfar*znear)/(zfar-znear), w: 0.0 },
+                }
+            }
+            Projection::Orthographic => {
+                let f = 1.0 / (self.focus_distance() * (fov / 2.0).tan());
+                Matrix4 {
+                    x: Vector4{ x: f / self.aspect, y: 0.0, z:  0.0,                      w: 0.0 },
+                    y: Vector4{ x: 0.0,             y: f,   z:  0.0,                      w: 0.0 },
+                    z: Vector4{ x: 0.0,             y: 0.0, z:  h*2.0/(zfar-znear),       w: 0.0 },
+                    w: Vector4{ x: 0.0,             y: 0.0, z: -(zfar+znear)/(zfar-znear), w: 1.0 },
+                }
+            }
+        }
+    }
+
+    pub fn get_view_mat(&self) -> Matrix4<f32> {
+        let s = self.orientation.rotate_vector(Vector3::unit_x());
+        let u = self.orientation.rotate_vector(Vector3::unit_y());
+        let f = self.orientation.rotate_vector(FORWARD) * self.z_sign();
+        let pos = to_vector(self.pos);
 
         // note: remember that this is column-major, so the lines of code are actually columns
         Matrix4{
-            x: Vector4{ x: sn.0, y: u.0, z: f.0, w: 0.0 },
-            y: Vector4{ x: sn.1, y: u.1, z: f.1, w: 0.0 },
-            z: Vector4{ x: sn.2, y: u.2, z: f.2, w: 0.0 },
-            w: Vector4{ x:  p.0, y: p.1, z: p.2, w: 1.0 },
+            x: Vector4{ x: s.x, y: u.x, z: f.x, w: 0.0 },
+            y: Vector4{ x: s.y, y: u.y, z: f.y, w: 0.0 },
+            z: Vector4{ x: s.z, y: u.z, z: f.z, w: 0.0 },
+            w: Vector4{ x: -pos.dot(s), y: -pos.dot(u), z: -pos.dot(f), w: 1.0 },
         }
     }
 
@@ -109,95 +492,199 @@ impl CameraState {
         array4x4(self.get_view_mat())
     }
 
-    pub fn update(&mut self) {
-        let f = norm(&self.dir);
+    // Ease the field of view towards the zoom target, dollying if requested.
+    fn update_fov(&mut self, dt: f32) {
+        let old_fov = self.lens.fov;
+        if old_fov == self.target_fov {
+            return;
+        }
+        let mut fov = self.target_fov + (old_fov - self.target_fov) * (-self.speed.zoom * dt).exp();
+        if (fov - self.target_fov).abs() < 1e-4 {
+            fov = self.target_fov;
+        }
+        self.lens.fov = fov;
+        if self.dolly {
+            // Keep distance * tan(fov / 2) constant.
+            let d = self.focus_distance();
+            let new_d = d * (old_fov / 2.0).tan() / (fov / 2.0).tan();
+            match self.mode {
+                CameraMode::Orbit => {
+                    self.orbit_distance = new_d.clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
+                }
+                CameraMode::Free => self.pos += self.get_dir() * (d - new_d),
+            }
+        }
+    }
 
-        let up = Vec3(0.0, 1.0, 0.0);
+    pub fn update(&mut self, dt: f32) {
+        self.update_fov(dt);
+        if self.mode == CameraMode::Orbit {
+            self.update_orbit(dt);
+            return;
+        }
 
-        let s = Vec3(f.1 * up.2 - f.2 * up.1,
-                     f.2 * up.0 - f.0 * up.2,
-                     f.0 * up.1 - f.1 * up.0);
+        let s = to_vec3(self.orientation.rotate_vector(Vector3::unit_x()));
+        let u = to_vec3(self.orientation.rotate_vector(Vector3::unit_y()));
+        let f = to_vec3(self.orientation.rotate_vector(FORWARD));
 
-        let s = norm(&s);
+        fn axis(pos: bool, neg: bool) -> f32 {
+            (pos as i32 - neg as i32) as f32
+        }
+        let strafe = axis(self.moving_right, self.moving_left) + self.analog_move.0;
+        let rise = axis(self.moving_up, self.moving_down) + self.analog_move.1;
+        let walk = axis(self.moving_forward, self.moving_backward) + self.analog_move.2;
 
-        let u = Vec3(s.1 * f.2 - s.2 * f.1,
-                     s.2 * f.0 - s.0 * f.2,
-                     s.0 * f.1 - s.1 * f.0);
+        // Ease towards the requested velocity, or coast to a halt when idle.
+        let speed = &self.speed;
+        let target = s * (strafe * speed.strafe) + u * (rise * speed.strafe) + f * (walk * speed.walk);
+        let idle = strafe == 0.0 && rise == 0.0 && walk == 0.0;
+        let rate = if idle { speed.damping } else { speed.acceleration };
+        self.velocity = target + (self.velocity - target) * (-rate * dt).exp();
+        self.pos += self.velocity * dt;
 
-        let walk_speed = 0.01;
-        let strife_speed = 0.02;
-        let pan_speed = 0.001;
+        // Positive angles turn right, down and clockwise.
+        let yaw = self.rel_x * speed.mouse
+            + (axis(self.turning_right, self.turning_left) + self.analog_look.0) * speed.turn * dt;
+        let pitch = self.rel_y * speed.mouse
+            + (axis(self.turning_down, self.turning_up) + self.analog_look.1) * speed.pitch * dt;
+        let roll = axis(self.rolling_right, self.rolling_left) * speed.roll * dt;
+        self.rel_x = 0.0;
+        self.rel_y = 0.0;
 
-        if self.moving_up {
-            self.pos += u * strife_speed;
-        }
-        if self.moving_down {
-            self.pos -= u * strife_speed;
-        }
-        if self.moving_left {
-            self.pos -= s * strife_speed;
-        }
-        if self.moving_right {
-            self.pos += s * strife_speed;
-        }
-        if self.moving_forward {
-            self.pos += f * walk_speed;
-        }
-        if self.moving_backward {
-            self.pos -= f * walk_speed;
+        // Yaw around the world vertical axis so that the horizon doesn't tilt,
+        // pitch and roll around the camera's own axes.
+        self.orientation = (Quaternion::from_angle_y(Rad(-yaw))
+            * self.orientation
+            * Quaternion::from_angle_x(Rad(-pitch))
+            * Quaternion::from_angle_z(Rad(-roll)))
+            .normalize();
+
+        //println!("camera_pos = {:?}", self.pos);
+        //println!("camera_orientation = {:?}", self.orientation);
+    }
+
+    fn update_orbit(&mut self, dt: f32) {
+        fn axis(pos: bool, neg: bool) -> f32 {
+            (pos as i32 - neg as i32) as f32
         }
+        let speed = &self.speed;
 
-        if self.turning_left { self.rel_x -= 8; }
-        if self.turning_right { self.rel_x += 8; }
-        if self.turning_up { self.rel_y -= 2; }
-        if self.turning_down { self.rel_y += 2; }
-        let vx = -pan_speed * self.rel_x as f32;
-        let vy = -pan_speed * self.rel_y as f32;
-        self.dir = Vec3(f.0 * vx.cos() + f.2 * vx.sin(),
-                        f.1 * vy.cos() - f.2 * vy.sin(),
-                        f.1 * vy.sin() - f.0 * vx.sin() + f.2 * vx.cos() * vy.cos());
-        self.rel_x = 0;
-        self.rel_y = 0;
+        // Keys and gamepad orbit, zoom and pan too.
+        self.orbit_yaw += self.rel_x * speed.orbit
+            + (axis(self.turning_right, self.turning_left) + self.analog_look.0) * speed.turn * dt;
+        self.orbit_pitch += self.rel_y * speed.orbit
+            + (axis(self.turning_down, self.turning_up) + self.analog_look.1) * speed.pitch * dt;
+        self.orbit_pitch = self.orbit_pitch.clamp(-MAX_ORBIT_PITCH, MAX_ORBIT_PITCH);
+        let walk = axis(self.moving_forward, self.moving_backward) + self.analog_move.2;
+        self.orbit_distance *= 0.9f32.powf(self.zoom) * (-walk * dt).exp();
+        self.orbit_distance = self.orbit_distance.clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
 
-        //println!("camera_pos = {:?}", self.pos);
-        //println!("camera_dir = {:?}", self.dir);
+        self.orientation = Quaternion::from_angle_y(Rad(-self.orbit_yaw))
+            * Quaternion::from_angle_x(Rad(-self.orbit_pitch));
+        let s = to_vec3(self.orientation.rotate_vector(Vector3::unit_x()));
+        let u = to_vec3(self.orientation.rotate_vector(Vector3::unit_y()));
+        let f = to_vec3(self.orientation.rotate_vector(FORWARD));
+
+        // Drag the target along with the pointer, faster when zoomed out.
+        let strafe = axis(self.moving_right, self.moving_left) + self.analog_move.0;
+        let rise = axis(self.moving_up, self.moving_down) + self.analog_move.1;
+        let pan_scale = self.orbit_distance * 0.002;
+        self.orbit_target += s * (strafe * speed.strafe * dt - self.pan_x * pan_scale)
+                           + u * (rise * speed.strafe * dt + self.pan_y * pan_scale);
+        self.pos = self.orbit_target - f * self.orbit_distance;
+
+        self.rel_x = 0.0;
+        self.rel_y = 0.0;
+        self.pan_x = 0.0;
+        self.pan_y = 0.0;
+        self.zoom = 0.0;
     }
 
     pub fn process_input(&mut self, event: &WindowEvent) {
         //println!("camera event={:?}", event);
         match event {
-            WindowEvent::CursorMoved { position, .. }  => {
+            WindowEvent::CursorMoved { position, .. } => {
                 let (x, y) = (position.x as i32, position.y as i32);
                 if self.mouse_x == -1 {
                     // Set initial absolute position.
                     self.mouse_x = x;
                     self.mouse_y = y;
                 }
-                self.rel_x += x - self.mouse_x;
-                self.rel_y += y - self.mouse_y;
+                let (dx, dy) = ((x - self.mouse_x) as f32, (y - self.mouse_y) as f32);
+                match self.mode {
+                    CameraMode::Free if !self.mouse_grabbed => {
+                        self.rel_x += dx;
+                        self.rel_y += dy;
+                    }
+                    CameraMode::Orbit if self.rotating => {
+                        self.rel_x += dx;
+                        self.rel_y += dy;
+                    }
+                    CameraMode::Orbit if self.panning => {
+                        self.pan_x += dx;
+                        self.pan_y += dy;
+                    }
+                    _ => (),
+                }
                 self.mouse_x = x;
                 self.mouse_y = y;
             }
-            WindowEvent::KeyboardInput { input, .. } => {
-                let pressed = input.state == ElementState::Pressed;
-                let key = match input.virtual_keycode {
-                    Some(key) => key,
-                    None => return,
-                };
-                match key {
-                    VirtualKeyCode::Left => self.moving_left = pressed,
-                    VirtualKeyCode::Right => self.moving_right = pressed,
-                    VirtualKeyCode::Up => self.moving_up = pressed,
-                    VirtualKeyCode::Down => self.moving_down = pressed,
-                    VirtualKeyCode::W => self.moving_forward = pressed,
-                    VirtualKeyCode::S => self.moving_backward = pressed,
-                    VirtualKeyCode::A => self.turning_left = pressed,
-                    VirtualKeyCode::D => self.turning_right = pressed,
-                    VirtualKeyCode::R => self.turning_up = pressed,
-                    VirtualKeyCode::F => self.turning_down = pressed,
+            WindowEvent::MouseInput { state, button, .. } => {
+                let pressed = *state == ElementState::Pressed;
+                match button {
+                    MouseButton::Left => self.rotating = pressed,
+                    MouseButton::Right | MouseButton::Middle => self.panning = pressed,
                     _ => (),
                 }
-            },
+            }
+            WindowEvent::MouseWheel { delta, .. } if self.mode == CameraMode::Orbit => {
+                self.zoom += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / 40.0,
+                };
+            }
+            _ => (),
+        }
+    }
+
+    /// Raw mouse motion, only used while the pointer is grabbed.
+    pub fn process_mouse_motion(&mut self, dx: f64, dy: f64) {
+        if self.mouse_grabbed {
+            self.rel_x += dx as f32;
+            self.rel_y += dy as f32;
+        }
+    }
+
+    pub fn set_analog(&mut self, strafe: f32, up: f32, forward: f32, look_x: f32, look_y: f32) {
+        self.analog_move = Vec3(strafe, up, forward);
+        self.analog_look = (look_x, look_y);
+    }
+
+    pub fn process_action(&mut self, action: Action, pressed: bool) {
+        match action {
+            Action::MoveLeft => self.moving_left = pressed,
+            Action::MoveRight => self.moving_right = pressed,
+            Action::MoveUp => self.moving_up = pressed,
+            Action::MoveDown => self.moving_down = pressed,
+            Action::MoveForward => self.moving_forward = pressed,
+            Action::MoveBackward => self.moving_backward = pressed,
+            Action::TurnLeft => self.turning_left = pressed,
+            Action::TurnRight => self.turning_right = pressed,
+            Action::TurnUp => self.turning_up = pressed,
+            Action::TurnDown => self.turning_down = pressed,
+            Action::RollLeft => self.rolling_left = pressed,
+            Action::RollRight => self.rolling_right = pressed,
+            Action::ZoomIn if pressed => self.zoom_to(self.target_fov / ZOOM_STEP),
+            Action::ZoomOut if pressed => self.zoom_to(self.target_fov * ZOOM_STEP),
+            Action::DollyZoomIn if pressed => self.dolly_zoom_to(self.target_fov / ZOOM_STEP),
+            Action::DollyZoomOut if pressed => self.dolly_zoom_to(self.target_fov * ZOOM_STEP),
+            Action::ToggleProjection if pressed => self.toggle_projection(),
+            Action::ToggleOrbit if pressed => {
+                self.set_mode(match self.mode {
+                    CameraMode::Free => CameraMode::Orbit,
+                    CameraMode::Orbit => CameraMode::Free,
+                });
+            }
             _ => (),
         }
     }
@@ -0,0 +1,457 @@
+// Minimal in-process sync server speaking the GNU Rocket protocol.
+//
+// It plays the role of the Rocket editor, so track edits, seeking and pausing
+// can be scripted from tests and tools without running the GUI tracker.
+// The demo side keeps using rust-rocket's RocketClient (see timer.rs);
+// SyncClient below is a bare-bones stand-in for it.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Where RocketClient::new() expects to find the editor.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:1338";
+
+const CLIENT_GREETING: &[u8] = b"hello, synctracker!";
+const SERVER_GREETING: &[u8] = b"hello, demo!";
+
+const SET_KEY: u8 = 0;
+const DELETE_KEY: u8 = 1;
+const GET_TRACK: u8 = 2;
+const SET_ROW: u8 = 3;
+const PAUSE: u8 = 4;
+const SAVE_TRACKS: u8 = 5;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interpolation {
+    Step = 0,
+    Linear = 1,
+    Smooth = 2,
+    Ramp = 3,
+}
+
+impl Interpolation {
+    fn from_u8(i: u8) -> io::Result<Interpolation> {
+        match i {
+            0 => Ok(Interpolation::Step),
+            1 => Ok(Interpolation::Linear),
+            2 => Ok(Interpolation::Smooth),
+            3 => Ok(Interpolation::Ramp),
+            _ => Err(invalid_data("unknown interpolation")),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Key {
+    pub row: u32,
+    pub value: f32,
+    pub interp: Interpolation,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u32(buf: &[u8]) -> u32 {
+    u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]])
+}
+
+// Appends whatever is readable without blocking to buf.
+// Returns false if the peer closed the connection.
+fn read_available(stream: &mut TcpStream, buf: &mut Vec<u8>) -> io::Result<bool> {
+    let mut chunk = [0u8; 1024];
+    loop {
+        match stream.read(&mut chunk) {
+            Ok(0) => return Ok(false),
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Checks the start of a greeting: Ok(true) once all of expect has arrived,
+// Ok(false) while it's still incomplete.
+fn check_greeting(buf: &[u8], expect: &[u8]) -> io::Result<bool> {
+    let n = buf.len().min(expect.len());
+    if buf[..n] != expect[..n] {
+        return Err(invalid_data("bad rocket greeting"));
+    }
+    Ok(n == expect.len())
+}
+
+// Non-blocking from now on.
+fn set_streaming(stream: &mut TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(None)?;
+    stream.set_nodelay(true)?;
+    stream.set_nonblocking(true)
+}
+
+// Greets the peer and blocks until it greets back. The order doesn't matter:
+// each side only reads the greeting of the other after sending its own.
+fn handshake(stream: &mut TcpStream, send: &[u8], expect: &[u8]) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.write_all(send)?;
+    let mut greeting = vec![0u8; expect.len()];
+    stream.read_exact(&mut greeting)?;
+    check_greeting(&greeting, expect)?;
+    set_streaming(stream)
+}
+
+/// Messages received by the editor from the demo.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerEvent {
+    Connected,
+    Disconnected,
+    GetTrack(String),
+    SetRow(u32),
+}
+
+pub struct SyncServer {
+    listener: TcpListener,
+    stream: Option<TcpStream>,
+    // Until the demo's greeting is complete, which may take several polls.
+    greeting: bool,
+    inbuf: Vec<u8>,
+
+    tracks: BTreeMap<String, BTreeMap<u32, Key>>,
+    // Track names in the order the connected client asked for them.
+    // The position in this list is the track index used on the wire.
+    client_tracks: Vec<String>,
+
+    pub row: u32,
+    pub paused: bool,
+}
+
+impl SyncServer {
+    pub fn new() -> io::Result<SyncServer> {
+        SyncServer::bind(DEFAULT_ADDR)
+    }
+
+    /// Bind to a specific address. Use port 0 to let the OS pick a free one.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<SyncServer> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(SyncServer {
+            listener,
+            stream: None,
+            greeting: false,
+            inbuf: Vec::new(),
+            tracks: BTreeMap::new(),
+            client_tracks: Vec::new(),
+            row: 0,
+            paused: true,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some() && !self.greeting
+    }
+
+    /// Block until a demo connects and completes the handshake.
+    pub fn accept(&mut self) -> io::Result<()> {
+        self.listener.set_nonblocking(false)?;
+        let res = self.listener.accept();
+        self.listener.set_nonblocking(true)?;
+        let mut stream = res?.0;
+        handshake(&mut stream, SERVER_GREETING, CLIENT_GREETING)?;
+        self.stream = Some(stream);
+        self.inbuf.clear();
+        self.start_session()
+    }
+
+    fn start_session(&mut self) -> io::Result<()> {
+        self.greeting = false;
+        self.client_tracks.clear();
+        let (row, paused) = (self.row, self.paused);
+        self.set_row(row)?;
+        self.pause(paused)
+    }
+
+    /// Accept new connections and process pending requests without blocking.
+    pub fn poll(&mut self) -> io::Result<Option<ServerEvent>> {
+        if self.stream.is_none() {
+            match self.listener.accept() {
+                Ok((mut stream, _)) => {
+                    set_streaming(&mut stream)?;
+                    self.stream = Some(stream);
+                    self.greeting = true;
+                    self.inbuf.clear();
+                    self.send(SERVER_GREETING)?;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let alive = read_available(self.stream.as_mut().unwrap(), &mut self.inbuf)?;
+        if self.greeting {
+            let complete = match check_greeting(&self.inbuf, CLIENT_GREETING) {
+                Ok(complete) => complete,
+                Err(err) => {
+                    self.stream = None;
+                    return Err(err);
+                }
+            };
+            if complete {
+                self.inbuf.drain(..CLIENT_GREETING.len());
+                self.start_session()?;
+                return Ok(Some(ServerEvent::Connected));
+            }
+            if !alive {
+                self.stream = None;
+            }
+            return Ok(None);
+        }
+        if let Some(event) = self.parse_request()? {
+            return Ok(Some(event));
+        }
+        if !alive {
+            self.stream = None;
+            return Ok(Some(ServerEvent::Disconnected));
+        }
+        Ok(None)
+    }
+
+    fn parse_request(&mut self) -> io::Result<Option<ServerEvent>> {
+        let buf = &self.inbuf;
+        let (event, len) = match buf.first() {
+            None => return Ok(None),
+            Some(&GET_TRACK) => {
+                if buf.len() < 5 {
+                    return Ok(None);
+                }
+                let name_len = read_u32(&buf[1..]) as usize;
+                if buf.len() < 5 + name_len {
+                    return Ok(None);
+                }
+                let name = String::from_utf8(buf[5..5 + name_len].to_vec())
+                    .map_err(|_| invalid_data("track name is not utf-8"))?;
+                (ServerEvent::GetTrack(name), 5 + name_len)
+            }
+            Some(&SET_ROW) => {
+                if buf.len() < 5 {
+                    return Ok(None);
+                }
+                (ServerEvent::SetRow(read_u32(&buf[1..])), 5)
+            }
+            Some(cmd) => return Err(invalid_data(&format!("unknown rocket command {}", cmd))),
+        };
+        self.inbuf.drain(..len);
+
+        match event {
+            ServerEvent::GetTrack(ref name) => self.send_track(name)?,
+            ServerEvent::SetRow(row) => self.row = row,
+            _ => (),
+        }
+        Ok(Some(event))
+    }
+
+    fn send(&mut self, msg: &[u8]) -> io::Result<()> {
+        match self.stream {
+            Some(ref mut stream) => {
+                // The socket is non-blocking, but messages are tiny.
+                stream.set_nonblocking(false)?;
+                let res = stream.write_all(msg);
+                stream.set_nonblocking(true)?;
+                res
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn send_track(&mut self, name: &str) -> io::Result<()> {
+        let index = match self.client_tracks.iter().position(|n| n == name) {
+            Some(index) => index,
+            None => {
+                self.client_tracks.push(name.to_string());
+                self.client_tracks.len() - 1
+            }
+        };
+        let keys: Vec<Key> = match self.tracks.get(name) {
+            Some(keys) => keys.values().cloned().collect(),
+            None => Vec::new(),
+        };
+        for key in keys {
+            self.send_key(index as u32, &key)?;
+        }
+        Ok(())
+    }
+
+    fn send_key(&mut self, index: u32, key: &Key) -> io::Result<()> {
+        let mut msg = vec![SET_KEY];
+        msg.extend_from_slice(&index.to_be_bytes());
+        msg.extend_from_slice(&key.row.to_be_bytes());
+        msg.extend_from_slice(&key.value.to_bits().to_be_bytes());
+        msg.push(key.interp as u8);
+        self.send(&msg)
+    }
+
+    fn client_index(&self, track: &str) -> Option<u32> {
+        self.client_tracks.iter().position(|n| n == track).map(|i| i as u32)
+    }
+
+    pub fn track_names(&self) -> Vec<String> {
+        self.tracks.keys().cloned().collect()
+    }
+
+    pub fn get_keys(&self, track: &str) -> Vec<Key> {
+        match self.tracks.get(track) {
+            Some(keys) => keys.values().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn set_key(&mut self, track: &str, row: u32, value: f32, interp: Interpolation) -> io::Result<()> {
+        let key = Key { row, value, interp };
+        self.tracks.entry(track.to_string()).or_default().insert(row, key);
+        match self.client_index(track) {
+            Some(index) => self.send_key(index, &key),
+            None => Ok(()),
+        }
+    }
+
+    pub fn delete_key(&mut self, track: &str, row: u32) -> io::Result<()> {
+        if let Some(keys) = self.tracks.get_mut(track) {
+            keys.remove(&row);
+        }
+        match self.client_index(track) {
+            Some(index) => {
+                let mut msg = vec![DELETE_KEY];
+                msg.extend_from_slice(&index.to_be_bytes());
+                msg.extend_from_slice(&row.to_be_bytes());
+                self.send(&msg)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Seek the demo to the given row.
+    pub fn set_row(&mut self, row: u32) -> io::Result<()> {
+        self.row = row;
+        let mut msg = vec![SET_ROW];
+        msg.extend_from_slice(&row.to_be_bytes());
+        self.send(&msg)
+    }
+
+    pub fn pause(&mut self, paused: bool) -> io::Result<()> {
+        self.paused = paused;
+        self.send(&[PAUSE, paused as u8])
+    }
+
+    /// Ask the demo to export its tracks.
+    pub fn save_tracks(&mut self) -> io::Result<()> {
+        self.send(&[SAVE_TRACKS])
+    }
+}
+
+/// Messages received by the demo from the editor.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientEvent {
+    SetKey { track: u32, key: Key },
+    DeleteKey { track: u32, row: u32 },
+    SetRow(u32),
+    Pause(bool),
+    SaveTracks,
+}
+
+/// Stand-in for the demo side of the protocol, for tests and tools which
+/// need to talk to a SyncServer without pulling in rust-rocket.
+pub struct SyncClient {
+    stream: TcpStream,
+    inbuf: Vec<u8>,
+}
+
+impl SyncClient {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<SyncClient> {
+        let mut stream = TcpStream::connect(addr)?;
+        handshake(&mut stream, CLIENT_GREETING, SERVER_GREETING)?;
+        Ok(SyncClient { stream, inbuf: Vec::new() })
+    }
+
+    fn send(&mut self, msg: &[u8]) -> io::Result<()> {
+        self.stream.set_nonblocking(false)?;
+        let res = self.stream.write_all(msg);
+        self.stream.set_nonblocking(true)?;
+        res
+    }
+
+    pub fn get_track(&mut self, name: &str) -> io::Result<()> {
+        let mut msg = vec![GET_TRACK];
+        msg.extend_from_slice(&(name.len() as u32).to_be_bytes());
+        msg.extend_from_slice(name.as_bytes());
+        self.send(&msg)
+    }
+
+    pub fn set_row(&mut self, row: u32) -> io::Result<()> {
+        let mut msg = vec![SET_ROW];
+        msg.extend_from_slice(&row.to_be_bytes());
+        self.send(&msg)
+    }
+
+    /// Returns the next complete message, if any, without blocking.
+    pub fn poll(&mut self) -> io::Result<Option<ClientEvent>> {
+        if !read_available(&mut self.stream, &mut self.inbuf)? && self.inbuf.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "editor went away"));
+        }
+        let buf = &self.inbuf;
+        let (event, len) = match buf.first() {
+            None => return Ok(None),
+            Some(&SET_KEY) => {
+                if buf.len() < 14 {
+                    return Ok(None);
+                }
+                let key = Key {
+                    row: read_u32(&buf[5..]),
+                    value: f32::from_bits(read_u32(&buf[9..])),
+                    interp: Interpolation::from_u8(buf[13])?,
+                };
+                (ClientEvent::SetKey { track: read_u32(&buf[1..]), key }, 14)
+            }
+            Some(&DELETE_KEY) => {
+                if buf.len() < 9 {
+                    return Ok(None);
+                }
+                (ClientEvent::DeleteKey { track: read_u32(&buf[1..]), row: read_u32(&buf[5..]) }, 9)
+            }
+            Some(&SET_ROW) => {
+                if buf.len() < 5 {
+                    return Ok(None);
+                }
+                (ClientEvent::SetRow(read_u32(&buf[1..])), 5)
+            }
+            Some(&PAUSE) => {
+                if buf.len() < 2 {
+                    return Ok(None);
+                }
+                (ClientEvent::Pause(buf[1] != 0), 2)
+            }
+            Some(&SAVE_TRACKS) => (ClientEvent::SaveTracks, 1),
+            Some(cmd) => return Err(invalid_data(&format!("unknown rocket command {}", cmd))),
+        };
+        self.inbuf.drain(..len);
+        Ok(Some(event))
+    }
+
+    /// Poll until a message arrives or the timeout expires.
+    pub fn wait(&mut self, timeout: Duration) -> io::Result<Option<ClientEvent>> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            if let Some(event) = self.poll()? {
+                return Ok(Some(event));
+            }
+            if std::time::Instant::now() > deadline {
+                return Ok(None);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
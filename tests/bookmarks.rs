@@ -0,0 +1,29 @@
+use cgmath::Quaternion;
+use mandelwow_lib::bookmarks::*;
+use mandelwow_lib::support::vec3::Vec3;
+
+#[test]
+fn parse_and_print_round_trip() {
+    let mut bookmarks = Bookmarks::new();
+    bookmarks.set(1, Bookmark {
+        pos: Vec3(0.5, -1.0, 2.25),
+        orientation: Quaternion::new(0.5, 0.5, -0.5, 0.5),
+        fov: 1.25,
+        t: 42.5,
+        wow: -0.3,
+    });
+    bookmarks.set(0, Bookmark {
+        pos: Vec3(0.0, 0.0, 0.0),
+        orientation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+        fov: 1.5,
+        t: 0.0,
+        wow: 0.0,
+    });
+    let parsed = Bookmarks::parse(&bookmarks.to_string()).unwrap();
+    assert_eq!(parsed, bookmarks);
+    assert_eq!(parsed.get(1).unwrap().t, 42.5);
+    assert!(parsed.get(2).is_none());
+
+    assert!(Bookmarks::parse("10  0 0 0  1 0 0 0  1  0  0\n").is_err());
+    assert!(Bookmarks::parse("1  0 0 0  1 0 0 0  1  0\n").is_err());
+}
@@ -0,0 +1,111 @@
+use cgmath::{Matrix4, Vector4};
+use mandelwow_lib::support::camera::*;
+use mandelwow_lib::support::vec3::Vec3;
+
+fn clip(camera: &CameraState, p: Vec3) -> Vector4<f32> {
+    let m: Matrix4<f32> = camera.get_perspview().into();
+    let c = m * Vector4::new(p.0, p.1, p.2, 1.0);
+    c / c.w
+}
+
+fn assert_close(a: Vector4<f32>, b: Vector4<f32>) {
+    assert!((a - b).x.abs() < 1e-4 && (a - b).y.abs() < 1e-4 && (a - b).z.abs() < 1e-4,
+            "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn handedness_renders_the_same_image() {
+    let mut camera = CameraState::new();
+    camera.look_at(Vec3(1.0, 0.5, 2.0), Vec3(0.0, 0.0, -3.0));
+    let p = Vec3(0.3, -0.2, -2.5);
+    for &projection in &[Projection::Perspective, Projection::Orthographic] {
+        let lens = Lens { projection, ..Lens::default() };
+        camera.set_lens(lens);
+        let left = clip(&camera, p);
+        camera.set_lens(Lens { handedness: Handedness::Right, ..lens });
+        assert_close(left, clip(&camera, p));
+        assert!(left.z > -1.0 && left.z < 1.0);
+    }
+}
+
+#[test]
+fn dolly_zoom_keeps_the_target_size() {
+    let mut camera = CameraState::new();
+    camera.set_orbit_target(Vec3(0.0, 0.0, -3.0));
+    camera.look_at(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, -3.0));
+    let edge = Vec3(0.5, 0.0, -3.0);
+    let before = clip(&camera, edge);
+
+    camera.dolly_zoom_to(0.5);
+    for _ in 0..200 {
+        camera.update(0.02);
+    }
+    assert_eq!(camera.get_fov(), 0.5);
+    assert!(camera.get_pos().2 > 3.0);
+    let after = clip(&camera, edge);
+    assert!((before.x - after.x).abs() < 1e-4 && after.y == 0.0);
+}
+
+fn assert_finite(camera: &CameraState) {
+    let m = camera.get_perspview();
+    assert!(m.iter().flatten().all(|v| v.is_finite()), "{:?}", m);
+}
+
+fn right(camera: &CameraState) -> Vec3 {
+    let view = camera.get_view();
+    Vec3(view[0][0], view[1][0], view[2][0])
+}
+
+#[test]
+fn looking_straight_up_or_down_keeps_the_heading() {
+    for &target in &[Vec3(0.0, 5.0, 0.0), Vec3(0.0, -5.0, 0.0)] {
+        let mut camera = CameraState::new();
+        camera.look_at(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, -3.0));
+        camera.look_at(Vec3(0.0, 0.0, 0.0), target);
+        assert_finite(&camera);
+        let dir = camera.get_dir();
+        assert!((dir.1 - target.1.signum()).abs() < 1e-5, "{:?}", dir);
+        let r = right(&camera);
+        assert!((r.0 - 1.0).abs() < 1e-5, "flipped: {:?}", r);
+    }
+}
+
+#[test]
+fn turning_over_the_pole_is_smooth() {
+    let mut camera = CameraState::new();
+    camera.process_action(mandelwow_lib::keymap::Action::TurnUp, true);
+    let mut last = right(&camera);
+    for _ in 0..500 {
+        camera.update(0.02);
+        assert_finite(&camera);
+        let r = right(&camera);
+        assert!(r.0 * last.0 + r.1 * last.1 + r.2 * last.2 > 0.99, "{:?} -> {:?}", last, r);
+        last = r;
+    }
+}
+
+#[test]
+fn orbit_keeps_the_target_distance() {
+    let mut camera = CameraState::new();
+    let target = Vec3(0.5, -0.5, -3.0);
+    camera.set_orbit_target(target);
+    camera.look_at(Vec3(0.0, 1.0, 1.0), target);
+    camera.set_mode(CameraMode::Orbit);
+    let distance = |camera: &CameraState| {
+        let (p, t) = (camera.get_pos(), camera.get_target());
+        ((p.0 - t.0).powi(2) + (p.1 - t.1).powi(2) + (p.2 - t.2).powi(2)).sqrt()
+    };
+    let start = distance(&camera);
+    assert!((start - (0.25f32 + 2.25 + 16.0).sqrt()).abs() < 1e-4, "{}", start);
+    // Spin around and push the pitch past both poles.
+    for &look in &[(1.0, 0.0), (0.3, 1.0), (-0.5, -1.0)] {
+        camera.set_analog(0.0, 0.0, 0.0, look.0, look.1);
+        for _ in 0..300 {
+            camera.update(0.02);
+            assert_finite(&camera);
+            assert!((distance(&camera) - start).abs() < 1e-4);
+            let up = camera.get_view();
+            assert!(up[1][1] > 0.0, "upside down: {:?}", up);
+        }
+    }
+}
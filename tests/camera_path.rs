@@ -0,0 +1,54 @@
+use mandelwow_lib::camera_path::*;
+use mandelwow_lib::support::vec3::Vec3;
+
+fn key(t: f32, x: f32, fov: f32) -> Keyframe {
+    Keyframe { t, pos: Vec3(x, 0.0, 0.0), target: Vec3(0.0, 0.0, -3.0), fov }
+}
+
+#[test]
+fn spline_passes_through_keyframes() {
+    let mut path = CameraPath::new();
+    path.add(key(2.0, 4.0, 1.0));
+    path.add(key(0.0, 0.0, 1.0));
+    path.add(key(1.0, 1.0, 1.5));
+    path.add(key(4.0, 2.0, 1.0));
+
+    for k in path.keys().to_vec() {
+        assert_eq!(path.sample(k.t), Some(k));
+    }
+    // Hold still outside the path.
+    assert_eq!(path.sample(-1.0).unwrap().pos, Vec3(0.0, 0.0, 0.0));
+    assert_eq!(path.sample(9.0).unwrap().pos, Vec3(2.0, 0.0, 0.0));
+
+    // Smooth in between: no jumps across a keyframe.
+    let before = path.sample(0.999).unwrap().pos.0;
+    let after = path.sample(1.001).unwrap().pos.0;
+    assert!((before - after).abs() < 0.01);
+    assert!(CameraPath::new().sample(0.0).is_none());
+}
+
+#[test]
+fn parse_and_print_round_trip() {
+    let mut path = CameraPath::new();
+    path.add(key(0.0, 0.0, 1.5));
+    path.add(key(2.5, -1.25, 0.75));
+    assert_eq!(CameraPath::parse(&path.to_string()).unwrap(), path);
+    assert!(CameraPath::parse("0 1 2 3\n").is_err());
+}
+
+#[test]
+fn non_finite_numbers() {
+    for line in &["nan 0 0 0 0 0 -3 1", "0 inf 0 0 0 0 -3 1", "0 0 0 0 0 0 -3 NaN"] {
+        assert!(CameraPath::parse(line).is_err(), "{}", line);
+    }
+
+    let mut path = CameraPath::new();
+    path.add(key(0.0, 0.0, 1.0));
+    path.add(key(f32::NAN, 5.0, 1.0));
+    path.add(key(f32::INFINITY, 5.0, 1.0));
+    path.add(key(2.0, 2.0, 1.0));
+    assert_eq!(path.keys().len(), 2);
+    assert_eq!(path.sample(f32::NAN), None);
+    assert_eq!(path.sample(f32::INFINITY).unwrap().pos, Vec3(2.0, 0.0, 0.0));
+    assert_eq!(path.sample(f32::NEG_INFINITY).unwrap().pos, Vec3(0.0, 0.0, 0.0));
+}
@@ -0,0 +1,120 @@
+// Headless OpenGL context for tests and benches.
+//
+// Uses Mesa's software rasterizer through EGL on the surfaceless platform,
+// so it needs neither a GPU nor a window system: results are the same on
+// every machine with the same Mesa version.
+//
+// It's a desktop GL context, like the one glutin creates for the window
+// with --gl gl:3.3: Mesa accepts our GLSL ES shaders there through
+// ARB_ES3_compatibility. new_gles makes a GLES 3.0 one like the default
+// --gl gles:3.0, where glium allows fewer formats (e.g. no half floats).
+
+#![allow(dead_code)]
+
+use glium::backend::{Backend, Context, Facade};
+use khronos_egl as egl;
+use std::os::raw::c_void;
+use std::rc::Rc;
+
+type Egl = egl::DynamicInstance<egl::EGL1_5>;
+
+// From EGL_MESA_platform_surfaceless.
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+struct EglBackend {
+    egl: Egl,
+    display: egl::Display,
+    context: egl::Context,
+    size: (u32, u32),
+}
+
+unsafe impl Backend for EglBackend {
+    fn swap_buffers(&self) -> Result<(), glium::SwapBuffersError> {
+        Ok(())
+    }
+
+    unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        self.egl.get_proc_address(symbol).map_or(std::ptr::null(), |f| f as *const c_void)
+    }
+
+    fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+        self.size
+    }
+
+    fn is_current(&self) -> bool {
+        self.egl.get_current_context() == Some(self.context)
+    }
+
+    unsafe fn make_current(&self) {
+        self.egl.make_current(self.display, None, None, Some(self.context)).unwrap();
+    }
+}
+
+impl Drop for EglBackend {
+    fn drop(&mut self) {
+        let _ = self.egl.make_current(self.display, None, None, None);
+        let _ = self.egl.destroy_context(self.display, self.context);
+    }
+}
+
+/// A GL context with no default framebuffer: render into a RenderTarget.
+pub struct Headless {
+    context: Rc<Context>,
+}
+
+impl Facade for Headless {
+    fn get_context(&self) -> &Rc<Context> {
+        &self.context
+    }
+}
+
+impl Headless {
+    /// `size` is what the scene code sees as the window size.
+    pub fn new(size: (u32, u32)) -> Result<Headless, String> {
+        Headless::with_api(size, egl::OPENGL_API, egl::OPENGL_BIT, &[egl::NONE])
+    }
+
+    /// A GLES 3.0 context.
+    pub fn new_gles(size: (u32, u32)) -> Result<Headless, String> {
+        let version = [egl::CONTEXT_MAJOR_VERSION, 3, egl::CONTEXT_MINOR_VERSION, 0, egl::NONE];
+        Headless::with_api(size, egl::OPENGL_ES_API, egl::OPENGL_ES3_BIT, &version)
+    }
+
+    fn with_api(size: (u32, u32), api: egl::Enum, renderable: egl::Int, context_attribs: &[egl::Int])
+            -> Result<Headless, String> {
+        // Always rasterize in software, even when a GPU is around.
+        std::env::set_var("LIBGL_ALWAYS_SOFTWARE", "1");
+
+        let lib = unsafe { libloading::Library::new("libEGL.so.1") }
+            .map_err(|err| format!("can't load libEGL: {}", err))?;
+        let egl = unsafe { Egl::load_required_from(lib) }.map_err(|err| err.to_string())?;
+        let display = unsafe {
+            egl.get_platform_display(PLATFORM_SURFACELESS_MESA, egl::DEFAULT_DISPLAY, &[egl::ATTRIB_NONE])
+        }.map_err(|err| format!("no EGL surfaceless platform: {}", err))?;
+        egl.initialize(display).map_err(|err| format!("eglInitialize: {}", err))?;
+        egl.bind_api(api).map_err(|err| err.to_string())?;
+        let attribs = [
+            egl::SURFACE_TYPE, egl::PBUFFER_BIT,
+            egl::RENDERABLE_TYPE, renderable,
+            egl::NONE,
+        ];
+        let config = egl
+            .choose_first_config(display, &attribs)
+            .map_err(|err| err.to_string())?
+            .ok_or("no matching EGL config")?;
+        let context = egl
+            .create_context(display, config, None, context_attribs)
+            .map_err(|err| format!("eglCreateContext: {}", err))?;
+        egl.make_current(display, None, None, Some(context)).map_err(|err| err.to_string())?;
+
+        let backend = EglBackend { egl, display, context, size };
+        let context = unsafe { Context::new(backend, false, Default::default()) }
+            .map_err(|err| format!("{:?}", err))?;
+        Ok(Headless { context })
+    }
+
+    /// Wait for the GPU to be done with all the queued commands.
+    pub fn finish(&self) {
+        self.context.finish();
+    }
+}
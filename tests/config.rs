@@ -0,0 +1,51 @@
+use mandelwow_lib::config::*;
+use mandelwow_lib::support::vec3::Vec3;
+
+#[test]
+fn parse_and_print_round_trip() {
+    let text = "\
+# comment
+[display]
+size = 1920x1080
+vsync=off
+
+[camera]
+pos = 1 2.5 -3
+";
+    let config = Config::parse(text).unwrap();
+    assert_eq!(config.get("display", "size"), Some("1920x1080"));
+    assert_eq!(config.get("display", "vsync"), Some("off"));
+    assert_eq!(config.get("display", "fullscreen"), None);
+    assert_eq!(config.get_vec3("camera", "pos"), Some(Vec3(1.0, 2.5, -3.0)));
+    assert_eq!(Config::parse(&config.to_string()).unwrap(), config);
+}
+
+#[test]
+fn parse_errors_report_line() {
+    let err = Config::parse("[display]\nsize 640x480\n").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(Config::parse("[display\n").is_err());
+}
+
+#[test]
+fn broken_file_is_not_overwritten() {
+    let dir = std::env::temp_dir().join(format!("mandelwow-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let broken = dir.join("broken.conf");
+    std::fs::write(&broken, "[display\nsize = 640x480\n").unwrap();
+    let mut config = Config::load_or_empty(broken.clone());
+    assert_eq!(config.path(), None);
+    config.set("display", "letterbox", true);
+    config.save().unwrap();
+    assert_eq!(std::fs::read_to_string(&broken).unwrap(), "[display\nsize = 640x480\n");
+
+    let missing = dir.join("missing.conf");
+    let mut config = Config::load_or_empty(missing.clone());
+    assert_eq!(config.path(), Some(missing.as_path()));
+    config.set("display", "letterbox", true);
+    config.save().unwrap();
+    assert_eq!(Config::load_from(&missing).unwrap().get("display", "letterbox"), Some("true"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,42 @@
+use mandelwow_lib::config::Config;
+use mandelwow_lib::gamepad::{apply_deadzone, GamepadSettings};
+
+fn length((x, y): (f32, f32)) -> f32 {
+    (x * x + y * y).sqrt()
+}
+
+#[test]
+fn radial_deadzone() {
+    assert_eq!(apply_deadzone(0.0, 0.0, 0.2), (0.0, 0.0));
+    assert_eq!(apply_deadzone(0.1, -0.1, 0.2), (0.0, 0.0));
+    assert_eq!(apply_deadzone(0.0, 0.2, 0.2), (0.0, 0.0));
+
+    // Motion starts from zero just past the edge...
+    assert!(length(apply_deadzone(0.0, 0.201, 0.2)) < 0.01);
+    // ...keeps its direction...
+    let (x, y) = apply_deadzone(0.3, 0.4, 0.2);
+    assert!((x / y - 0.75).abs() < 1e-5, "{} {}", x, y);
+    // ...and reaches 1 at full deflection, also along the diagonals.
+    assert!((length(apply_deadzone(1.0, 0.0, 0.2)) - 1.0).abs() < 1e-5);
+    assert!((length(apply_deadzone(-0.9, 0.9, 0.2)) - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn settings_from_config() {
+    let settings = |text: &str| GamepadSettings::from_config(&Config::parse(text).unwrap());
+    assert_eq!(settings("").unwrap(), GamepadSettings::default());
+    let s = settings("[gamepad]\ndeadzone = 0.3\nlook_sensitivity = 2\ninvert_y = on\n").unwrap();
+    assert_eq!(s.deadzone, 0.3);
+    assert_eq!(s.look_sensitivity, 2.0);
+    assert!(s.invert_y);
+
+    assert!(settings("[gamepad]\ndeadzone = 1\n").is_err());
+    assert!(settings("[gamepad]\ndeadzone = -0.1\n").is_err());
+    assert!(settings("[gamepad]\ndeadzone = nan\n").is_err());
+    assert!(settings("[gamepad]\nmove_sensitivity = fast\n").is_err());
+    assert!(settings("[gamepad]\ninvert_y = maybe\n").is_err());
+    for v in &["0", "-1", "nan", "inf"] {
+        assert!(settings(&format!("[gamepad]\nmove_sensitivity = {}\n", v)).is_err(), "{}", v);
+        assert!(settings(&format!("[gamepad]\nlook_sensitivity = {}\n", v)).is_err(), "{}", v);
+    }
+}
@@ -0,0 +1,210 @@
+// Golden-image tests: render fixed frames headlessly and compare them with
+// the reference images in tests/golden/, on a desktop GL context and on a
+// GLES one like the default --gl gles:3.0.
+//
+// A missing reference is a failure. After an intended change in the
+// rendering, or to add a case, regenerate them all with:
+//
+//   MANDELWOW_BLESS=1 cargo test --test golden
+//
+// Without headless GL the test fails too, unless MANDELWOW_SKIP_GOLDEN is set.
+//
+// On a mismatch the actual frame and a diff image are written next to the
+// test binaries, in target/tmp/golden/, prefixed with gles- for GLES.
+
+#![cfg(feature = "png")]
+
+mod common;
+
+use common::Headless;
+use mandelwow_lib::options::{Options, Quality};
+use mandelwow_lib::render_target::RenderTarget;
+use mandelwow_lib::retro;
+use mandelwow_lib::support::camera::CameraState;
+use mandelwow_lib::support::vec3::Vec3;
+use mandelwow_lib::world::{World, MODEL_Z};
+use std::path::{Path, PathBuf};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 180;
+
+// A pixel is visibly different above this CIE76 distance...
+const MAX_DELTA_E: f32 = 10.0;
+// ...and a frame fails if more than this fraction of its pixels are.
+const MAX_DIFFERENT_PIXELS: f32 = 0.005;
+
+struct Case {
+    name: &'static str,
+    t: f32,
+    pos: Vec3,
+    target: Vec3,
+    quality: Quality,
+    retro: bool,
+}
+
+const CASES: &[Case] = &[
+    Case { name: "start", t: 0.0, pos: Vec3(0.0, 0.0, 0.0), target: Vec3(0.0, 0.0, MODEL_Z), quality: Quality::Medium, retro: false },
+    Case { name: "fractal_close", t: 7.5, pos: Vec3(0.5, 0.5, -1.2), target: Vec3(0.0, 0.0, MODEL_Z), quality: Quality::High, retro: false },
+    Case { name: "sea_low", t: 21.0, pos: Vec3(-3.0, 1.0, 1.0), target: Vec3(0.0, -2.5, -12.0), quality: Quality::Low, retro: false },
+    Case { name: "retro", t: 7.5, pos: Vec3(-3.0, 1.0, 1.0), target: Vec3(0.0, 0.0, MODEL_Z), quality: Quality::Low, retro: true },
+];
+
+/// RGBA pixels, top row first.
+struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+fn render(facade: &Headless, case: &Case) -> Image {
+    let options = Options { quality: case.quality, retro: case.retro, ..Default::default() };
+    let world = World::new(facade, &options);
+    let mut camera = CameraState::new();
+    if case.retro {
+        camera.set_aspect(retro::WIDTH as f32 / retro::HEIGHT as f32);
+    } else {
+        camera.set_aspect(WIDTH as f32 / HEIGHT as f32);
+    }
+    camera.look_at(case.pos, case.target);
+
+    let target = RenderTarget::new(facade, WIDTH, HEIGHT);
+    world.draw_frame(facade, &mut target.framebuffer(facade), &camera, case.t);
+    let pixels = target
+        .read_pixels()
+        .chunks_exact(WIDTH as usize * 4)
+        .rev()
+        .flatten()
+        .copied()
+        .collect();
+    Image { width: WIDTH, height: HEIGHT, pixels }
+}
+
+fn read_png(path: &Path) -> Image {
+    let decoder = png::Decoder::new(std::fs::File::open(path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba, "{}", path.display());
+    pixels.truncate(info.buffer_size());
+    Image { width: info.width, height: info.height, pixels }
+}
+
+fn write_png(path: &Path, image: &Image) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    let mut encoder = png::Encoder::new(file, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&image.pixels).unwrap();
+}
+
+// sRGB to CIE L*a*b* (D65).
+fn lab(p: &[u8]) -> [f32; 3] {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    let (r, g, b) = (linear(p[0]), linear(p[1]), linear(p[2]));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.950_47;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.088_83;
+    let f = |t: f32| if t > 0.008_856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn delta_e(a: &[u8], b: &[u8]) -> f32 {
+    let (a, b) = (lab(a), lab(b));
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// Fraction of visibly different pixels, and an image highlighting them in
+/// red over a dimmed copy of the reference.
+fn compare(actual: &Image, expected: &Image) -> (f32, Image) {
+    let mut diff = Vec::with_capacity(expected.pixels.len());
+    let mut different = 0;
+    for (a, e) in actual.pixels.chunks_exact(4).zip(expected.pixels.chunks_exact(4)) {
+        if delta_e(a, e) > MAX_DELTA_E {
+            different += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = (e[0] as u32 * 3 + e[1] as u32 * 6 + e[2] as u32) / 40;
+            diff.extend_from_slice(&[luma as u8, luma as u8, luma as u8, 255]);
+        }
+    }
+    let fraction = different as f32 / (expected.width * expected.height) as f32;
+    (fraction, Image { width: expected.width, height: expected.height, pixels: diff })
+}
+
+fn reference_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name))
+}
+
+// Headless GL, or None when skipping.
+fn headless(gles: bool) -> Option<Headless> {
+    let facade = if gles { Headless::new_gles((WIDTH, HEIGHT)) } else { Headless::new((WIDTH, HEIGHT)) };
+    match facade {
+        Ok(facade) => Some(facade),
+        Err(err) if std::env::var_os("MANDELWOW_SKIP_GOLDEN").is_some() => {
+            eprintln!("warning: skipping golden image tests, no headless GL: {}", err);
+            None
+        }
+        Err(err) => panic!("no headless GL for the golden image tests \
+                            (set MANDELWOW_SKIP_GOLDEN to skip them): {}", err),
+    }
+}
+
+// Render all the cases and compare them with the references, or write them
+// when blessing. `prefix` goes before the names of the failing frames.
+fn check(facade: &Headless, prefix: &str, bless: bool) {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    let mut failures = Vec::new();
+    for case in CASES {
+        let actual = render(facade, case);
+        let reference = reference_path(case.name);
+        if bless {
+            write_png(&reference, &actual);
+            eprintln!("Wrote reference image {}", reference.display());
+            continue;
+        }
+        if !reference.exists() {
+            failures.push(format!("{}: no reference image {}, run with MANDELWOW_BLESS=1 to create it",
+                                  case.name, reference.display()));
+            continue;
+        }
+        let expected = read_png(&reference);
+        assert_eq!((expected.width, expected.height), (WIDTH, HEIGHT), "{}", reference.display());
+        let (different, diff) = compare(&actual, &expected);
+        if different > MAX_DIFFERENT_PIXELS {
+            let actual_path = out_dir.join(format!("{}{}-actual.png", prefix, case.name));
+            let diff_path = out_dir.join(format!("{}{}-diff.png", prefix, case.name));
+            write_png(&actual_path, &actual);
+            write_png(&diff_path, &diff);
+            failures.push(format!("{}{}: {:.2}% of the pixels differ, see {} and {}",
+                                  prefix, case.name, different * 100.0,
+                                  actual_path.display(), diff_path.display()));
+        }
+    }
+    assert!(failures.is_empty(), "golden images differ:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn golden_images() {
+    if let Some(facade) = headless(false) {
+        check(&facade, "", std::env::var_os("MANDELWOW_BLESS").is_some());
+    }
+}
+
+// The same frames on the default --gl gles:3.0, where glium has no half
+// float buffers, depth-only render buffers or uniform blocks. The references
+// come from the desktop context.
+#[test]
+fn golden_images_on_gles() {
+    if std::env::var_os("MANDELWOW_BLESS").is_some() {
+        return;
+    }
+    if let Some(facade) = headless(true) {
+        check(&facade, "gles-", false);
+    }
+}
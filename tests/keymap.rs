@@ -0,0 +1,31 @@
+use glium::glutin::event::{MouseButton, VirtualKeyCode};
+use mandelwow_lib::keymap::*;
+use mandelwow_lib::Config;
+
+#[test]
+fn config_overrides_default_bindings() {
+    let config = Config::parse("[keys]\nscreenshot = F12, Mouse:Middle\nquit = Q\n").unwrap();
+    let keymap = Keymap::from_config(&config).unwrap();
+
+    assert_eq!(keymap.action(Binding::Key(VirtualKeyCode::F12)), Some(Action::Screenshot));
+    assert_eq!(keymap.action(Binding::Mouse(MouseButton::Middle)), Some(Action::Screenshot));
+    assert_eq!(keymap.action(Binding::Key(VirtualKeyCode::F10)), None);
+    assert_eq!(keymap.bindings(Action::Quit), vec![Binding::Key(VirtualKeyCode::Q)]);
+    // Untouched actions keep their defaults.
+    assert_eq!(keymap.action(Binding::Key(VirtualKeyCode::W)), Some(Action::MoveForward));
+}
+
+#[test]
+fn bad_bindings_are_rejected() {
+    let config = Config::parse("[keys]\nfly = W\n").unwrap();
+    assert!(Keymap::from_config(&config).is_err());
+    let config = Config::parse("[keys]\nquit = Hyper\n").unwrap();
+    assert!(Keymap::from_config(&config).is_err());
+}
+
+#[test]
+fn binding_names_round_trip() {
+    for &name in &["Escape", "F11", "PageUp", "Key1", "Mouse:Left", "Mouse:4"] {
+        assert_eq!(Binding::parse(name).unwrap().to_string(), name);
+    }
+}
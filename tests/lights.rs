@@ -0,0 +1,46 @@
+use mandelwow_lib::config::Config;
+use mandelwow_lib::lights::{Light, Lights, MAX_LIGHTS};
+
+fn lights(text: &str) -> Result<Lights, String> {
+    Lights::from_config(&Config::parse(text).unwrap())
+}
+
+#[test]
+fn defaults_without_section() {
+    assert_eq!(lights("").unwrap(), Lights::default());
+}
+
+#[test]
+fn lights_from_config() {
+    let l = lights("[lights]\nambient = 0.2 0.1 0\nspecular = 0.5\nshininess = 8\n\
+                    light2 = point 1 2 3  1 0.5 0  0.1\nlight1 = directional 0 1 0  2 2 2\n").unwrap();
+    assert_eq!(l.ambient, [0.2, 0.1, 0.0]);
+    assert_eq!(l.specular, 0.5);
+    assert_eq!(l.shininess, 8.0);
+    assert_eq!(l.lights, vec![
+        Light::Directional { direction: [0.0, 1.0, 0.0], color: [2.0, 2.0, 2.0] },
+        Light::Point { position: [1.0, 2.0, 3.0], color: [1.0, 0.5, 0.0], attenuation: 0.1 },
+    ]);
+}
+
+#[test]
+fn invalid_lights() {
+    assert!(lights("[lights]\nlight1 = spot 0 1 0 1 1 1\n").is_err());
+    assert!(lights("[lights]\nlight1 = point 0 1 0 1 1 1\n").is_err());
+    assert!(lights("[lights]\nlight1 = point 0 1 0 1 1 1 -1\n").is_err());
+    assert!(lights("[lights]\nshininess = 0.5\n").is_err());
+    assert!(lights("[lights]\nambient = 1 1\n").is_err());
+    assert!(lights(&format!("[lights]\nlight{} = directional 0 1 0 1 1 1\n", MAX_LIGHTS + 1)).is_err());
+}
+
+#[test]
+fn uniform_array_layout() {
+    let array = Lights::default().uniform_array();
+    assert_eq!(array[0], [0.1, 0.1, 0.1, 32.0]);
+    assert_eq!(array[1], [0.3, 2.0, 0.0, 0.0]);
+    assert_eq!(array[2], [-0.5, 0.7, 0.6, 0.0]);
+    assert_eq!(array[3], [0.8, 0.8, 0.8, 0.0]);
+    assert_eq!(array[4], [0.0, 2.0, 0.0, 1.0]);
+    assert_eq!(array[5], [1.0, 1.0, 1.0, 0.05]);
+    assert_eq!(array[6], [0.0; 4]);
+}
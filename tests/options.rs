@@ -0,0 +1,68 @@
+use mandelwow_lib::options::{Options, Quality};
+use mandelwow_lib::Config;
+
+fn parse(args: &[&str]) -> Result<Options, String> {
+    let mut options = Options::default();
+    options.parse_args(args.iter().copied()).map_err(|err| err.to_string())?;
+    Ok(options)
+}
+
+#[test]
+fn seed_and_quality() {
+    assert_eq!(Options::default().seed, None);
+    assert_eq!(parse(&["--seed", "42"]).unwrap().seed, Some(42));
+    assert_eq!(parse(&["--seed=7"]).unwrap().seed, Some(7));
+    assert!(parse(&["--seed", "-1"]).is_err());
+
+    assert_eq!(Options::default().quality.slices(), Quality::Medium.slices());
+    assert_eq!(parse(&["--quality", "low"]).unwrap().quality.slices(), 15);
+    assert_eq!(parse(&["-q", "high"]).unwrap().quality.slices(), 60);
+    assert!(parse(&["--quality", "ultra"]).is_err());
+}
+
+#[test]
+fn start_time_and_looping() {
+    assert_eq!(parse(&["--start", "12.5"]).unwrap().start_time, 12.5);
+    assert_eq!(parse(&["-t", "3"]).unwrap().start_time, 3.0);
+    assert!(parse(&["--start", "-1"]).is_err());
+    assert!(parse(&["--start", "nan"]).is_err());
+    assert!(parse(&["--start", "inf"]).is_err());
+    assert_eq!(parse(&["--start", "1e30"]).unwrap().start_time, 1e30);
+
+    assert!(Options::default().looping);
+    assert!(!parse(&["--no-loop"]).unwrap().looping);
+    assert!(parse(&["--no-loop", "--loop"]).unwrap().looping);
+}
+
+#[test]
+fn camera_path_playback_is_opt_in() {
+    assert!(!Options::default().play_path);
+    assert!(parse(&["--play-path"]).unwrap().play_path);
+
+    let mut options = Options::default();
+    options.apply_config(&Config::parse("[camera]\nplay = on\n").unwrap()).unwrap();
+    assert!(options.play_path);
+    assert!(Options::default().apply_config(&Config::parse("[camera]\nplay = later\n").unwrap()).is_err());
+}
+
+#[test]
+fn bad_arguments_are_rejected() {
+    assert_eq!(parse(&["--fly"]).unwrap_err(), "unknown option: --fly");
+    assert_eq!(parse(&["--seed"]).unwrap_err(), "missing value for --seed");
+    assert_eq!(parse(&["-m"]).unwrap_err(), "missing value for -m");
+}
+
+#[test]
+fn command_line_overrides_config() {
+    let config = Config::parse("[render]\nquality = low\nseed = 3\n[audio]\nloop = off\n").unwrap();
+    let mut options = Options::default();
+    options.apply_config(&config).unwrap();
+    assert_eq!(options.quality, Quality::Low);
+    assert_eq!(options.seed, Some(3));
+    assert!(!options.looping);
+
+    options.parse_args(["--quality", "high", "--loop"].iter().copied()).unwrap();
+    assert_eq!(options.quality, Quality::High);
+    assert_eq!(options.seed, Some(3));
+    assert!(options.looping);
+}
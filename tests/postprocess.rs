@@ -0,0 +1,138 @@
+mod common;
+
+use glium::Rect;
+use glium::Surface;
+use mandelwow_lib::config::Config;
+use glium::texture::UncompressedFloatFormat;
+use mandelwow_lib::postprocess::{Pass, PostProcess, PostScenes, PostSettings, Tonemap};
+use mandelwow_lib::render_target::{self, RenderTarget};
+
+fn settings(text: &str) -> Result<PostSettings, String> {
+    PostSettings::from_config(&Config::parse(text).unwrap())
+}
+
+#[test]
+fn default_chain() {
+    let default = settings("").unwrap();
+    assert_eq!(default, PostSettings::default());
+    assert!(default.passes.is_empty());
+    assert_eq!(default.tonemap, Tonemap::Clamp);
+    assert_eq!(default.exposure, 1.0);
+}
+
+#[test]
+fn chain_from_config() {
+    let s = settings("[postprocess]\npasses = film_grain, bloom, color_grading\n\
+                      bloom = 0.8 1.5\ncolor_grading = 1.1 0.5 1 0.9 0.8\ntonemap = reinhard\nexposure = 2\n").unwrap();
+    assert_eq!(s.passes, vec![
+        Pass::FilmGrain { amount: 0.04 },
+        Pass::Bloom { threshold: 0.8, intensity: 1.5 },
+        Pass::ColorGrading { contrast: 1.1, saturation: 0.5, gain: [1.0, 0.9, 0.8] },
+    ]);
+    assert_eq!(s.tonemap, Tonemap::Reinhard);
+    assert_eq!(s.exposure, 2.0);
+
+    // Parameters don't enable a pass on their own.
+    let s = settings("[postprocess]\nvignette = 0.5 0.2\n").unwrap();
+    assert!(s.passes.is_empty());
+    let s = settings("[postprocess]\nvignette = 0.5 0.2\npasses = bloom, vignette\n").unwrap();
+    assert_eq!(s.passes[1], Pass::Vignette { strength: 0.5, radius: 0.2 });
+
+    assert!(settings("[postprocess]\npasses =\n").unwrap().passes.is_empty());
+}
+
+#[test]
+fn invalid_chain() {
+    assert!(settings("[postprocess]\npasses = bloom, blur\n").is_err());
+    assert!(settings("[postprocess]\nbloom = 1\n").is_err());
+    assert!(settings("[postprocess]\ntonemap = filmic\n").is_err());
+    assert!(settings("[postprocess]\nexposure = 0\n").is_err());
+    assert!(settings("[postprocess]\nsharpen = 1\n").is_err());
+    assert!(settings("[postprocess]\nstart = 1\n").is_err());
+}
+
+fn scenes(text: &str) -> Result<PostScenes, String> {
+    PostScenes::from_config(&Config::parse(text).unwrap())
+}
+
+#[test]
+fn chains_per_scene() {
+    let s = scenes("[postprocess]\npasses = vignette, bloom\nbloom = 0.8 1.5\ntonemap = aces\n\
+                    [postprocess.outro]\nstart = 90\npasses = film_grain\nexposure = 2\n\
+                    [postprocess.drop]\nstart = 30.5\nbloom = 2 0.5\n").unwrap();
+    assert_eq!(s.scenes.iter().map(|scene| scene.name.as_str()).collect::<Vec<_>>(), ["drop", "outro"]);
+    assert_eq!(s.scenes[0].start, 30.5);
+
+    assert_eq!(s.at(0.0), &s.default);
+    assert_eq!(s.at(30.0), &s.default);
+    // The passes of [postprocess], with the parameters of the scene.
+    let drop = s.at(30.5);
+    assert_eq!(drop.passes, vec![
+        Pass::Vignette { strength: 0.3, radius: 0.75 },
+        Pass::Bloom { threshold: 2.0, intensity: 0.5 },
+    ]);
+    assert_eq!(drop.tonemap, Tonemap::Aces);
+    let outro = s.at(1000.0);
+    assert_eq!(outro.passes, vec![Pass::FilmGrain { amount: 0.04 }]);
+    assert_eq!((outro.exposure, outro.tonemap), (2.0, Tonemap::Aces));
+
+    assert_eq!(scenes("").unwrap(), PostScenes::from(PostSettings::default()));
+}
+
+#[test]
+fn invalid_scenes() {
+    assert!(scenes("[postprocess.intro]\npasses = bloom\n").is_err());
+    assert!(scenes("[postprocess.intro]\nstart = -1\n").is_err());
+    assert!(scenes("[postprocess.intro]\nstart = inf\n").is_err());
+    assert!(scenes("[postprocess.intro]\nstart = 1\npasses = blur\n").is_err());
+    assert!(scenes("[postprocess.intro]\nstart = 1\n[postprocess.drop]\nstart = 1.0\n").is_err());
+}
+
+// Run the chain on a uniformly gray picture, return the RGBA pixels.
+fn apply(facade: &common::Headless, settings: &PostSettings) -> Vec<u8> {
+    let scene = RenderTarget::new_hdr(facade, 64, 36);
+    scene.framebuffer(facade).clear_color_and_depth((0.5, 0.5, 0.5, 1.0), 1.0);
+    let target = RenderTarget::new(facade, 64, 36);
+    let post = PostProcess::new(facade, &settings.clone().into());
+    let rect = Rect { left: 0, bottom: 0, width: 64, height: 36 };
+    post.apply(facade, scene.color(), &mut target.framebuffer(facade), &rect, 0.0);
+    target.read_pixels()
+}
+
+#[test]
+fn passes_on_gpu() {
+    let facade = match common::Headless::new((64, 36)) {
+        Ok(facade) => facade,
+        Err(err) => {
+            eprintln!("Skipping, no headless GL: {}", err);
+            return;
+        }
+    };
+    let plain = PostSettings { passes: Vec::new(), exposure: 1.0, tonemap: Tonemap::Clamp };
+    let pixels = apply(&facade, &plain);
+    assert!(pixels.chunks(4).all(|p| p == [128, 128, 128, 255] || p == [127, 127, 127, 255]),
+            "{:?}", &pixels[..4]);
+
+    let vignette = PostSettings { passes: vec![Pass::Vignette { strength: 0.5, radius: 0.5 }], ..plain };
+    let pixels = apply(&facade, &vignette);
+    let pixel = |x: usize, y: usize| pixels[(y * 64 + x) * 4];
+    assert!(pixel(32, 18) >= 127, "the center should stay as is: {}", pixel(32, 18));
+    assert!(pixel(0, 0) < 100, "the corners should be darker: {}", pixel(0, 0));
+}
+
+// The default --gl gles:3.0, where glium can't render into half floats.
+#[test]
+fn passes_on_gles() {
+    let facade = match common::Headless::new_gles((64, 36)) {
+        Ok(facade) => facade,
+        Err(err) => {
+            eprintln!("Skipping, no headless GLES: {}", err);
+            return;
+        }
+    };
+    assert_eq!(render_target::hdr_format(&facade), UncompressedFloatFormat::U8U8U8U8);
+    let bloom = PostSettings { passes: vec![Pass::Bloom { threshold: 1.0, intensity: 0.6 }], ..Default::default() };
+    let pixels = apply(&facade, &bloom);
+    assert!(pixels.chunks(4).all(|p| p == [128, 128, 128, 255] || p == [127, 127, 127, 255]),
+            "{:?}", &pixels[..4]);
+}
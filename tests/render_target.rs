@@ -0,0 +1,12 @@
+use glium::Rect;
+use mandelwow_lib::render_target::{letterbox, WIDESCREEN};
+
+#[test]
+fn letterbox_centers_a_widescreen_picture() {
+    // Bars above and below.
+    assert_eq!(letterbox(1280, 1024, WIDESCREEN), Rect { left: 0, bottom: 152, width: 1280, height: 720 });
+    // Bars on the sides.
+    assert_eq!(letterbox(2560, 1080, WIDESCREEN), Rect { left: 320, bottom: 0, width: 1920, height: 1080 });
+    // Nothing to do.
+    assert_eq!(letterbox(1920, 1080, WIDESCREEN), Rect { left: 0, bottom: 0, width: 1920, height: 1080 });
+}
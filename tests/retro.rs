@@ -0,0 +1,79 @@
+mod common;
+
+use glium::{Rect, Surface};
+use mandelwow_lib::config::Config;
+use mandelwow_lib::render_target::RenderTarget;
+use mandelwow_lib::retro::{screen_rect, RetroFilter, RetroSettings, HEIGHT, WIDTH};
+use std::collections::HashSet;
+
+#[test]
+fn whole_pixels_in_the_middle() {
+    let rect = |left, bottom, width, height| Rect { left, bottom, width, height };
+    assert_eq!(screen_rect(&rect(0, 0, 1280, 720)), rect(160, 60, 960, 600));
+    assert_eq!(screen_rect(&rect(0, 0, 1920, 1080)), rect(160, 40, 1600, 1000));
+    assert_eq!(screen_rect(&rect(0, 0, 640, 400)), rect(0, 0, 640, 400));
+    // Within the letterbox.
+    assert_eq!(screen_rect(&rect(0, 152, 1280, 720)), rect(160, 212, 960, 600));
+    // Too small for a whole C64 screen.
+    assert_eq!(screen_rect(&rect(0, 0, 160, 120)), rect(0, 10, 160, 100));
+}
+
+#[test]
+fn settings_from_config() {
+    let settings = |text: &str| RetroSettings::from_config(&Config::parse(text).unwrap());
+    assert_eq!(settings("").unwrap(), RetroSettings::default());
+    let s = settings("[retro]\ndither = 0\nscanlines = 0.5\ncurvature = 0.1\n").unwrap();
+    assert_eq!(s, RetroSettings { dither: 0.0, scanlines: 0.5, curvature: 0.1 });
+    assert_eq!(settings("[retro]\nscanlines = 3\n").unwrap().scanlines, 1.0);
+    assert!(settings("[retro]\ndither = -1\n").is_err());
+    assert!(settings("[retro]\ncurvature = flat\n").is_err());
+}
+
+// Show a uniformly gray C64 screen twice its size, return the RGBA pixels.
+fn draw(facade: &common::Headless, settings: &RetroSettings) -> Vec<u8> {
+    let filter = RetroFilter::new(facade, settings);
+    // Between the dark gray and the gray of the palette, nearer to the former.
+    let gray = (96.0f32 / 255.0).powf(2.2);
+    filter.framebuffer(facade).clear_color(gray, gray, gray, 1.0);
+    let target = RenderTarget::new(facade, WIDTH * 2, HEIGHT * 2);
+    let rect = Rect { left: 0, bottom: 0, width: WIDTH * 2, height: HEIGHT * 2 };
+    filter.draw(&mut target.framebuffer(facade), &rect);
+    target.read_pixels()
+}
+
+#[test]
+fn palette_on_gpu() {
+    match common::Headless::new((64, 36)) {
+        Ok(facade) => check_palette(&facade),
+        Err(err) => eprintln!("Skipping, no headless GL: {}", err),
+    }
+}
+
+// The default --gl gles:3.0, where glium can't use uniform blocks.
+#[test]
+fn palette_on_gles() {
+    match common::Headless::new_gles((64, 36)) {
+        Ok(facade) => check_palette(&facade),
+        Err(err) => eprintln!("Skipping, no headless GLES: {}", err),
+    }
+}
+
+fn check_palette(facade: &common::Headless) {
+    let plain = RetroSettings { dither: 0.0, ..Default::default() };
+    let pixels = draw(facade, &plain);
+    let colors: HashSet<_> = pixels.chunks(4).collect();
+    assert_eq!(colors.len(), 1, "{:?}", colors);
+
+    let pixels = draw(facade, &RetroSettings::default());
+    let colors: HashSet<_> = pixels.chunks(4).collect();
+    assert_eq!(colors.len(), 2, "dithered between two grays: {:?}", colors);
+    // Every C64 pixel covers 2x2 pixels of the same color.
+    let pixel = |x: u32, y: u32| &pixels[((y * WIDTH * 2 + x) * 4) as usize..][..4];
+    for y in (0..HEIGHT * 2).step_by(2) {
+        for x in (0..WIDTH * 2).step_by(2) {
+            assert_eq!(pixel(x, y), pixel(x + 1, y));
+            assert_eq!(pixel(x, y), pixel(x, y + 1));
+            assert_eq!(pixel(x, y), pixel(x + 1, y + 1));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use cgmath::Quaternion;
+use mandelwow_lib::config::Config;
+use mandelwow_lib::screenshot::*;
+use mandelwow_lib::support::vec3::Vec3;
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn names_are_timestamped() {
+    let t = UNIX_EPOCH + Duration::from_millis(1_496_698_212_345);
+    assert_eq!(timestamped_name(t), "mandelwow-20170605-213012-345.png");
+    let t = UNIX_EPOCH + Duration::from_secs(951_782_400);  // Leap day.
+    assert_eq!(timestamped_name(t), "mandelwow-20000229-000000-000.png");
+}
+
+#[test]
+fn settings_from_config() {
+    let settings = |text: &str| ScreenshotSettings::from_config(&Config::parse(text).unwrap());
+    assert!(!settings("").unwrap().alpha);
+    for v in &["on", "yes", "true", "1"] {
+        assert!(settings(&format!("[screenshot]\nalpha = {}\n", v)).unwrap().alpha, "{}", v);
+    }
+    assert!(!settings("[screenshot]\nalpha = off\n").unwrap().alpha);
+    assert!(settings("[screenshot]\nalpha = maybe\n").is_err());
+    assert_eq!(settings("[screenshot]\nscale = 2\n").unwrap().scale, 2);
+    assert!(settings("[screenshot]\nsupersample = 9\n").is_err());
+}
+
+#[test]
+fn downsample_averages_blocks() {
+    #[rustfmt::skip]
+    let pixels = [
+        0, 0, 0, 255,   100, 0, 0, 255,    10, 10, 10, 10,   10, 10, 10, 10,
+        0, 0, 0, 255,   100, 0, 0, 255,    10, 10, 10, 10,   10, 10, 10, 10,
+    ];
+    assert_eq!(downsample(&pixels, 4, 2, 2), vec![50, 0, 0, 255, 10, 10, 10, 10]);
+    assert_eq!(downsample(&pixels, 4, 2, 1), pixels.to_vec());
+}
+
+fn scene() -> SceneInfo {
+    SceneInfo {
+        t: 12.5,
+        hit_time: 11.75,
+        wow: -0.25,
+        pos: Vec3(1.0, 0.5, -2.0),
+        orientation: Quaternion::new(0.5, 0.5, 0.5, -0.5),
+        fov: 1.25,
+        formula: "z = z^2 + c".to_string(),
+        max_iter: 64,
+        slices: 30,
+        version: "0.7.0".to_string(),
+    }
+}
+
+#[test]
+fn scene_info_round_trip() {
+    let text = scene().to_text();
+    assert!(text.contains(&("Software".to_string(), "MandelWow 0.7.0".to_string())));
+    assert_eq!(SceneInfo::from_text(&text), Ok(scene()));
+    assert!(SceneInfo::from_text(&text[1..]).is_err());
+
+    let without = |key: &str| text.iter().filter(|(k, _)| k != key).cloned().collect::<Vec<_>>();
+    assert_eq!(SceneInfo::from_text(&without("mandelwow.hit_time")).unwrap().hit_time, 0.0);
+    let mut text = without("mandelwow.t");
+    text.push(("mandelwow.t".to_string(), "inf".to_string()));
+    assert!(SceneInfo::from_text(&text).is_err());
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn screenshots_carry_scene_info() {
+    let settings = ScreenshotSettings {
+        dir: std::env::temp_dir().join(format!("mandelwow-test-{}", std::process::id())),
+        ..Default::default()
+    };
+    let pixels = vec![255u8; 4 * 3 * 4];
+    let path = take_screenshot(&pixels, 4, 3, &settings, &scene()).unwrap();
+    assert_eq!(load_scene_info(&path).unwrap(), scene());
+    std::fs::remove_dir_all(&settings.dir).unwrap();
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use mandelwow_lib::config::Config;
+use mandelwow_lib::lights::Lights;
+use mandelwow_lib::render_target::RenderTarget;
+use mandelwow_lib::sea::{Sea, SeaSettings, Wave, MAX_SIZE, MAX_WAVES};
+use mandelwow_lib::support::camera::CameraState;
+use mandelwow_lib::support::vec3::Vec3;
+use std::f32::consts::PI;
+
+#[test]
+fn default_waves() {
+    let settings = SeaSettings::from_config(&Config::default()).unwrap();
+    assert_eq!(settings, SeaSettings::default());
+    // The sea as it has always been on the 40x25 grid.
+    for &(x, z, t) in &[(0.0, 0.0, 0.0), (3.0, 7.0, 1.5), (39.0, 24.0, 100.0)] {
+        let expected = ((x / 40.0 * PI * 5.0 + t * 2.0).sin() + (z / 25.0 * PI * 3.0 + t * 3.0).sin()) * 0.3;
+        assert!((settings.height(x, z, t) - expected).abs() < 1e-4, "{} {} {}", x, z, t);
+    }
+}
+
+#[test]
+fn waves_from_config() {
+    let config = Config::parse("[sea]\nwave2 = 0.1 0.5 90 1\nwave1 = 0.5 1 0 -2\n").unwrap();
+    let settings = SeaSettings::from_config(&config).unwrap();
+    assert_eq!(settings.waves, vec![
+        Wave { amplitude: 0.5, frequency: 1.0, direction: 0.0, speed: -2.0 },
+        Wave { amplitude: 0.1, frequency: 0.5, direction: 90.0, speed: 1.0 },
+    ]);
+    assert!((settings.height(1.0, 0.0, 0.0) - 0.5 * 1f32.sin()).abs() < 1e-6);
+    assert!((settings.height(0.0, 2.0, 0.0) - 0.1 * 1f32.sin()).abs() < 1e-6);
+}
+
+#[test]
+fn invalid_waves() {
+    for text in &[
+        "wave1 = 0.5 1 0",
+        "wave1 = 0.5 1 0 2 3",
+        "wave1 = -0.5 1 0 2",
+        "wave1 = big 1 0 2",
+        "wave0 = 0.5 1 0 2",
+        "wavex = 0.5 1 0 2",
+        "wave1 = nan 1 0 2",
+        "wave1 = 0.5 inf 0 2",
+        "wave1 = 0.5 1 0 2\nwave01 = 0.1 1 0 2",
+    ] {
+        let config = Config::parse(&format!("[sea]\n{}\n", text)).unwrap();
+        assert!(SeaSettings::from_config(&config).is_err(), "{}", text);
+    }
+    let config = Config::parse(&format!("[sea]\nwave{} = 0.5 1 0 2\n", MAX_WAVES + 1)).unwrap();
+    assert!(SeaSettings::from_config(&config).is_err());
+}
+
+#[test]
+fn grid_from_config() {
+    let config = Config::parse("[sea]\nsize = 100x50\nspacing = 1.5\norigin = -75 0\ny = -4\nscroll = 0.5 -3\n").unwrap();
+    let settings = SeaSettings::from_config(&config).unwrap();
+    assert_eq!((settings.columns, settings.rows), (100, 50));
+    assert_eq!(settings.spacing, 1.5);
+    assert_eq!(settings.origin, (-75.0, 0.0));
+    assert_eq!(settings.y, -4.0);
+    assert_eq!(settings.scroll, (0.5, -3.0));
+    assert_eq!(settings.waves, SeaSettings::default().waves);
+
+    let config = Config::parse("[sea]\nsize = 1024x1024\n").unwrap();
+    assert_eq!(SeaSettings::from_config(&config).unwrap().columns, MAX_SIZE);
+    for text in &["size = 0x10", "size = 10", "size = 100000x100000", "size = 1025x1",
+                  "spacing = 0", "spacing = -1", "spacing = inf", "origin = 1", "origin = nan 0",
+                  "y = nan", "y = -inf", "scroll = a b", "scroll = 0 inf"] {
+        let config = Config::parse(&format!("[sea]\n{}\n", text)).unwrap();
+        assert!(SeaSettings::from_config(&config).is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn scrolling_wraps_around() {
+    let settings = SeaSettings {
+        columns: 10,
+        rows: 4,
+        spacing: 2.0,
+        origin: (-10.0, -1.0),
+        y: -3.0,
+        scroll: (4.0, -2.0),
+        waves: Vec::new(),
+    };
+    let p = settings.position(0.0, 0.0, 0.0);
+    assert_eq!((p.x, p.y, p.z), (-10.0, -3.0, -1.0));
+    // One column to the right and half a row further after half a second.
+    let p = settings.position(0.0, 0.0, 0.5);
+    assert_eq!((p.x, p.z), (-8.0, -2.0));
+    // The last column comes back on the left.
+    let p = settings.position(9.0, 0.0, 0.5);
+    assert_eq!(p.x, -10.0);
+    // The whole grid is back in place after crossing 20 units in x and 8 in z.
+    let p = settings.position(3.0, 2.0, 20.0);
+    let q = settings.position(3.0, 2.0, 0.0);
+    assert_eq!((p.x, p.z), (q.x, q.z));
+}
+
+#[test]
+fn shaders_wrap_around() {
+    let facade = match common::Headless::new((160, 90)) {
+        Ok(facade) => facade,
+        Err(err) => {
+            eprintln!("Skipping, no headless GL: {}", err);
+            return;
+        }
+    };
+    let settings = SeaSettings { columns: 10, rows: 4, spacing: 2.0, scroll: (4.0, -2.0), ..Default::default() };
+    let sea = Sea::new(&facade, &SeaSettings { waves: Vec::new(), ..settings }, 0);
+    let mut camera = CameraState::new();
+    camera.set_aspect(160.0 / 90.0);
+    camera.look_at(Vec3(0.0, 2.0, 4.0), Vec3(0.0, -3.0, -5.0));
+    let target = RenderTarget::new(&facade, 160, 90);
+    let render = |t| {
+        let mut frame = target.framebuffer(&facade);
+        glium::Surface::clear_color_and_depth(&mut frame, (0.0, 0.0, 0.0, 1.0), 1.0);
+        sea.draw(&mut frame, &camera.get_perspview(), [0.0, 2.0, 4.0], t, &Lights::default(), None);
+        target.read_pixels()
+    };
+    let start = render(0.0);
+    assert_ne!(start, render(0.5), "the sea should move");
+    assert_eq!(start, render(20.0), "the sea should be back in place");
+}
+
+#[test]
+fn any_glyph_offset() {
+    let facade = match common::Headless::new((16, 16)) {
+        Ok(facade) => facade,
+        Err(err) => {
+            eprintln!("Skipping, no headless GL: {}", err);
+            return;
+        }
+    };
+    let settings = SeaSettings { columns: 10, rows: 4, ..Default::default() };
+    Sea::new(&facade, &settings, usize::MAX);
+}
+
+#[test]
+fn sea_bounds() {
+    let settings = SeaSettings { columns: 10, rows: 4, spacing: 2.0, ..Default::default() };
+    let (min, max) = settings.bounds();
+    for t in [0.0, 0.3, 1.7, 5.0] {
+        for x in 0..10 {
+            for z in 0..4 {
+                let p = settings.position(x as f32, z as f32, t);
+                assert!(p.x - 0.5 >= min.x && p.y - 0.5 >= min.y && p.z - 0.5 >= min.z, "{:?}", p);
+                assert!(p.x + 0.5 <= max.x && p.y + 0.5 <= max.y && p.z + 0.5 <= max.z, "{:?}", p);
+            }
+        }
+    }
+}
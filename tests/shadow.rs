@@ -0,0 +1,88 @@
+mod common;
+
+use cgmath::{InnerSpace, Point3, Vector3};
+use mandelwow_lib::lights::{Light, Lights};
+use mandelwow_lib::render_target::RenderTarget;
+use mandelwow_lib::sea::{Sea, SeaSettings, Wave};
+use mandelwow_lib::shadow::{light_matrices, ShadowMap};
+use mandelwow_lib::support::camera::CameraState;
+use mandelwow_lib::support::vec3::Vec3;
+
+#[test]
+fn light_covers_sphere() {
+    let center = Point3::new(1.0, -2.0, 3.0);
+    let direction = Vector3::new(-0.5, 0.7, 0.6);
+    let (view, projection) = light_matrices(direction, center, 10.0);
+    let clip = |p: Point3<f32>| {
+        let v = projection * view * p.to_homogeneous();
+        v.truncate() / v.w
+    };
+    // The point nearest to the light is on the near plane, the farthest on the far one.
+    let near = clip(center + direction.normalize() * 10.0);
+    let far = clip(center - direction.normalize() * 10.0);
+    assert!((near.z + 1.0).abs() < 1e-4, "{:?}", near);
+    assert!((far.z - 1.0).abs() < 1e-4, "{:?}", far);
+    for d in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z(), Vector3::new(1.0, -1.0, 1.0)] {
+        for p in [center + d.normalize() * 9.99, center - d.normalize() * 9.99] {
+            let c = clip(p);
+            assert!(c.x.abs() <= 1.0 && c.y.abs() <= 1.0 && c.z.abs() <= 1.0, "{:?} -> {:?}", p, c);
+        }
+    }
+    // Straight from above too.
+    let (view, projection) = light_matrices(Vector3::unit_y(), center, 10.0);
+    let c = projection * view * center.to_homogeneous();
+    assert!(c.x.is_finite() && c.y.is_finite() && c.z.is_finite(), "{:?}", c);
+}
+
+#[test]
+fn sea_shadows_itself() {
+    match common::Headless::new((160, 90)) {
+        Ok(facade) => check_sea_shadows(&facade),
+        Err(err) => eprintln!("Skipping, no headless GL: {}", err),
+    }
+}
+
+// The default --gl gles:3.0, where glium has no depth-only render buffers.
+#[test]
+fn sea_shadows_itself_on_gles() {
+    match common::Headless::new_gles((160, 90)) {
+        Ok(facade) => check_sea_shadows(&facade),
+        Err(err) => eprintln!("Skipping, no headless GLES: {}", err),
+    }
+}
+
+fn check_sea_shadows(facade: &common::Headless) {
+    // Rows of cubes alternately up and down, and a low light from the front
+    // so the high rows cast long shadows on the low ones.
+    let wave = Wave { amplitude: 1.0, frequency: std::f32::consts::PI / 2.0, direction: 90.0, speed: 0.0 };
+    let settings = SeaSettings { columns: 10, rows: 6, waves: vec![wave], ..Default::default() };
+    let sea = Sea::new(facade, &settings, 0);
+    let direction = [0.0, 0.15, 1.0];
+    let lights = Lights { lights: vec![Light::Directional { direction, color: [1.0; 3] }], ..Lights::default() };
+    let mut camera = CameraState::new();
+    camera.set_aspect(160.0 / 90.0);
+    camera.look_at(Vec3(-15.0, 2.0, 2.0), Vec3(-15.0, -2.5, -5.0));
+    let target = RenderTarget::new(facade, 160, 90);
+    let render = |shadow: Option<&ShadowMap>| {
+        let mut frame = target.framebuffer(facade);
+        glium::Surface::clear_color_and_depth(&mut frame, (0.0, 0.0, 0.0, 1.0), 1.0);
+        sea.draw(&mut frame, &camera.get_perspview(), [-15.0, 2.0, 2.0], 1.0, &lights, shadow);
+        target.read_pixels()
+    };
+
+    let (min, max) = settings.bounds();
+    let mut shadow = ShadowMap::new(facade, 512);
+    shadow.set_light(direction, ((min + max) / 2.0).into(), (max - min).magnitude() / 2.0);
+    let unlit = render(None);
+    assert_eq!(unlit, render(Some(&shadow)), "an empty map shouldn't cast shadows");
+
+    sea.draw_shadow(&mut shadow.framebuffer(facade), &shadow, 1.0);
+    let shadowed = render(Some(&shadow));
+    let brightness = |p: &[u8]| p[..3].iter().map(|&c| c as u32).sum::<u32>();
+    let darker = unlit.chunks(4).zip(shadowed.chunks(4))
+        .filter(|(a, b)| brightness(b) < brightness(a)).count();
+    let brighter = unlit.chunks(4).zip(shadowed.chunks(4))
+        .filter(|(a, b)| brightness(b) > brightness(a)).count();
+    assert!(darker > 100, "only {} darker pixels", darker);
+    assert_eq!(brighter, 0);
+}
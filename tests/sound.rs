@@ -0,0 +1,11 @@
+use mandelwow_lib::sound::has_ended;
+
+#[test]
+fn ends_after_one_playthrough_unless_looping() {
+    assert!(!has_ended(false, 0));
+    assert!(has_ended(false, 1));
+    // The loop count keeps going up while looping.
+    assert!(!has_ended(true, 0));
+    assert!(!has_ended(true, 1));
+    assert!(!has_ended(true, 7));
+}
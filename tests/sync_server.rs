@@ -0,0 +1,146 @@
+use mandelwow_lib::sync_server::*;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn connect() -> (SyncServer, SyncClient) {
+    let mut server = SyncServer::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    // The handshake only completes once the server accepts.
+    let handle = std::thread::spawn(move || {
+        server.accept().unwrap();
+        server
+    });
+    let client = SyncClient::connect(addr).unwrap();
+    (handle.join().unwrap(), client)
+}
+
+// Drain the initial row and pause state sent on connect.
+fn skip_hello(client: &mut SyncClient) {
+    assert_eq!(client.wait(TIMEOUT).unwrap(), Some(ClientEvent::SetRow(0)));
+    assert_eq!(client.wait(TIMEOUT).unwrap(), Some(ClientEvent::Pause(true)));
+}
+
+fn wait_server(server: &mut SyncServer) -> ServerEvent {
+    let deadline = std::time::Instant::now() + TIMEOUT;
+    while std::time::Instant::now() < deadline {
+        if let Some(event) = server.poll().unwrap() {
+            return event;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    panic!("no event from client");
+}
+
+#[test]
+fn track_keys_are_sent_on_request() {
+    let (mut server, mut client) = connect();
+    skip_hello(&mut client);
+
+    server.set_key("cam.x", 4, 1.5, Interpolation::Linear).unwrap();
+    server.set_key("cam.x", 0, -1.0, Interpolation::Step).unwrap();
+    client.get_track("cam.x").unwrap();
+    assert_eq!(wait_server(&mut server), ServerEvent::GetTrack("cam.x".to_string()));
+
+    let key0 = Key { row: 0, value: -1.0, interp: Interpolation::Step };
+    let key4 = Key { row: 4, value: 1.5, interp: Interpolation::Linear };
+    assert_eq!(client.wait(TIMEOUT).unwrap(), Some(ClientEvent::SetKey { track: 0, key: key0 }));
+    assert_eq!(client.wait(TIMEOUT).unwrap(), Some(ClientEvent::SetKey { track: 0, key: key4 }));
+
+    // Later edits go straight to the client.
+    server.delete_key("cam.x", 4).unwrap();
+    assert_eq!(client.wait(TIMEOUT).unwrap(), Some(ClientEvent::DeleteKey { track: 0, row: 4 }));
+    assert_eq!(server.get_keys("cam.x"), vec![key0]);
+}
+
+#[test]
+fn seek_and_pause() {
+    let (mut server, mut client) = connect();
+    skip_hello(&mut client);
+
+    server.set_row(42).unwrap();
+    server.pause(false).unwrap();
+    assert_eq!(client.wait(TIMEOUT).unwrap(), Some(ClientEvent::SetRow(42)));
+    assert_eq!(client.wait(TIMEOUT).unwrap(), Some(ClientEvent::Pause(false)));
+
+    client.set_row(43).unwrap();
+    assert_eq!(wait_server(&mut server), ServerEvent::SetRow(43));
+    assert_eq!(server.row, 43);
+
+    drop(client);
+    assert_eq!(wait_server(&mut server), ServerEvent::Disconnected);
+    assert!(!server.is_connected());
+}
+
+#[cfg(feature = "editor")]
+#[test]
+fn timer_follows_editor() {
+    use mandelwow_lib::Timer;
+
+    let mut server = SyncServer::new().unwrap();
+    // RocketClient connects synchronously, so accept from another thread.
+    let handle = std::thread::spawn(move || {
+        server.accept().unwrap();
+        server
+    });
+    let mut timer = Timer::new();
+    let mut server = handle.join().unwrap();
+    assert!(timer.rocket.is_some());
+
+    // The editor starts out paused.
+    std::thread::sleep(Duration::from_millis(50));
+    timer.update();
+    assert!(timer.pause);
+
+    server.set_row(50).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    timer.update();
+    assert_eq!(timer.t, 50.0 / timer.rows_per_second());
+
+    server.pause(false).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    timer.update();
+    assert!(!timer.pause);
+}
+
+#[test]
+fn polling_never_waits_for_the_greeting() {
+    use std::io::{Read, Write};
+
+    let mut server = SyncServer::bind("127.0.0.1:0").unwrap();
+    let mut demo = std::net::TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    let start = std::time::Instant::now();
+    for _ in 0..10 {
+        assert_eq!(server.poll().unwrap(), None);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    demo.write_all(b"hello, sync").unwrap();
+    for _ in 0..10 {
+        assert_eq!(server.poll().unwrap(), None);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(!server.is_connected());
+
+    demo.write_all(b"tracker!").unwrap();
+    assert_eq!(wait_server(&mut server), ServerEvent::Connected);
+    assert!(server.is_connected());
+    let mut greeting = [0u8; 12];
+    demo.read_exact(&mut greeting).unwrap();
+    assert_eq!(&greeting, b"hello, demo!");
+}
+
+#[test]
+fn bad_greeting_is_rejected() {
+    use std::io::Write;
+
+    let mut server = SyncServer::bind("127.0.0.1:0").unwrap();
+    let mut demo = std::net::TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    demo.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+    let deadline = std::time::Instant::now() + TIMEOUT;
+    while server.poll().is_ok() {
+        assert!(std::time::Instant::now() < deadline, "the greeting was accepted");
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(!server.is_connected());
+}
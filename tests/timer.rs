@@ -0,0 +1,22 @@
+use mandelwow_lib::timer::{Timer, DEFAULT_ROWS_PER_SECOND};
+
+#[test]
+fn tempo_changes_keep_the_row() {
+    let mut timer = Timer::new();
+    assert_eq!(timer.rows_per_second(), DEFAULT_ROWS_PER_SECOND);
+    // 125 bpm at speed 6: the XM defaults.
+    timer.set_tempo(125, 6);
+    assert!((timer.rows_per_second() - 25.0 / 3.0).abs() < 1e-5);
+    timer.t = 3.0;
+    assert!((timer.row() - 25.0).abs() < 1e-4);
+
+    // Twice as fast from row 25 on.
+    timer.set_tempo(125, 3);
+    assert!((timer.row() - 25.0).abs() < 1e-4);
+    timer.t = 4.5;
+    assert!((timer.row() - 50.0).abs() < 1e-4);
+
+    timer.set_row(30.0);
+    assert!((timer.t - 3.3).abs() < 1e-4);
+    assert!((timer.row() - 30.0).abs() < 1e-4);
+}
@@ -1,7 +1,10 @@
+use crate::uniform_array::Merge;
 use cgmath::conv::array4x4;
 use cgmath::Matrix4;
 use glium;
-use glium::{Display, Program, Surface, implement_vertex, texture, uniform};
+use glium::backend::Facade;
+use glium::uniforms::Uniforms;
+use glium::{Program, Surface, implement_vertex, texture, uniform};
 use std;
 
 fn gamma<T>(x: T) -> f32
@@ -12,7 +15,7 @@ where
     ((f32::from(x)) / 255.).powf(2.2)
 }
 
-fn srgb<T>(c: [T; 3]) -> [f32; 4]
+pub(crate) fn srgb<T>(c: [T; 3]) -> [f32; 4]
 where
     f32: From<T>,
     T: Copy,
@@ -37,13 +40,37 @@ fn c64_font() -> (u32, u32, Vec<u8>) {
     (128, 128, Vec::from(pixels))
 }
 
-pub fn text_program(display: &Display) -> Program {
+pub fn text_program<F: ?Sized + Facade>(display: &F) -> Program {
     //load_program(display, "shaders/text.vert", "shaders/text.frag");
     let vertex_shader_src = include_str!("shaders/text.vert");
     let fragment_shader_src = include_str!("shaders/text.frag");
     Program::from_source(display, vertex_shader_src, fragment_shader_src, None).unwrap()
 }
 
+/// The 16 colors of the VIC-II, in sRGB.
+// RGB values from http://unusedino.de/ec64/technical/misc/vic656x/colors/
+pub const PALETTE: [[u8; 3]; 16] = [
+    [  0,   0,   0],  //  0 - black
+    [255, 255, 255],  //  1 - white
+    [136,  57,  50],  //  2 - red
+    [103, 182, 189],  //  3 - cyan
+    [139,  63, 150],  //  4 - purple
+    [ 85, 160,  73],  //  5 - green
+    [ 64,  50, 133],  //  6 - blue
+    [191, 206, 114],  //  7 - yellow
+    [139,  84,  41],  //  8 - orange
+    [ 87,  66,   0],  //  9 - brown
+    [184, 105,  98],  // 10 - light red
+    [ 80,  80,  80],  // 11 - dark grey
+    [120, 120, 120],  // 12 - grey
+    [148, 224, 137],  // 13 - light green
+    [120, 106, 189],  // 14 - light blue
+    [159, 159, 159],  // 15 - light grey
+];
+
+fn bgcolor() -> [f32; 4] { srgb(PALETTE[6]) }
+fn fgcolor() -> [f32; 4] { srgb(PALETTE[14]) }
+
 #[derive(Copy, Clone)]
 struct Vertex {
     position: [f32; 2],
@@ -60,7 +87,7 @@ pub struct Text {
 }
 
 impl Text {
-    pub fn new(display: &Display) -> Text {
+    pub fn new<F: ?Sized + Facade>(display: &F) -> Text {
         let (w, h, pixels) = c64_font();
         let image = glium::texture::RawImage2d {
             data: std::borrow::Cow::from(pixels),
@@ -125,7 +152,7 @@ impl Text {
         }
     }
 
-    pub fn draw(&self, frame: &mut glium::Frame, c: char, model: &Matrix4<f32>, perspview: &[[f32; 4]; 4]) {
+    pub fn draw<S: Surface>(&self, frame: &mut S, c: char, model: &Matrix4<f32>, perspview: &[[f32; 4]; 4]) {
         let uniforms =
             uniform! {
             model: array4x4(*model),
@@ -133,9 +160,8 @@ impl Text {
             tex: self.tex.sampled()
                 .magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest),
             index: c as i32,
-            // RGB values from http://unusedino.de/ec64/technical/misc/vic656x/colors/
-            bgcolor: srgb([ 64,  50, 133u8]),  //  6 - blue
-            fgcolor: srgb([120, 106, 189u8]),  // 14 - light blue
+            bgcolor: bgcolor(),
+            fgcolor: fgcolor(),
         };
         frame
             .draw(
@@ -147,4 +173,27 @@ impl Text {
             )
             .unwrap();
     }
+
+    /// Draw all the characters in a single call, with a program taking
+    /// per-instance attributes. The font and colors are added to the given
+    /// uniforms.
+    pub fn draw_instanced<S, I, U>(&self, frame: &mut S, program: &Program,
+                                   instances: &glium::VertexBuffer<I>, uniforms: U)
+            where S: Surface, I: Copy, U: Uniforms {
+        let uniforms = Merge(uniforms, uniform! {
+            tex: self.tex.sampled()
+                .magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest),
+            bgcolor: bgcolor(),
+            fgcolor: fgcolor(),
+        });
+        frame
+            .draw(
+                (&self.vertex_buffer, instances.per_instance().unwrap()),
+                &self.index_buffer,
+                program,
+                &uniforms,
+                &self.params,
+            )
+            .unwrap();
+    }
 }
@@ -9,14 +9,15 @@ type Rocket = rust_rocket::client::RocketClient;
 #[cfg(not(feature = "editor"))]
 type Rocket = ();
 
-#[cfg(feature = "editor")]
-const BPS: f32 = 10.0;
+/// Rocket rows per second used when no module tempo is known.
+pub const DEFAULT_ROWS_PER_SECOND: f32 = 10.0;
 
 #[derive(Debug)]
 pub struct Timer {
     pub t: f32,          /// Simulation time (starts from 0 and does not advance while on pause).
     pub now: Instant,    /// Wall time, use instead of Instant::now() for frame-consistent time.
     prev_time: Instant,  /// Time of previous frame.
+    pub dt: f32,         /// Wall time elapsed since the previous frame, in seconds, even on pause.
     frame: u32,          /// Frame count, starts from 0 and does not increment while on pause.
 
     last_report_time: Instant,
@@ -27,6 +28,12 @@ pub struct Timer {
 
     pub pause: bool,
 
+    /// Rocket timeline resolution, see set_tempo().
+    rows_per_second: f32,
+    /// Row at t = 0 if the current row rate had applied all along, so that
+    /// tempo changes don't make the row jump.
+    row_offset: f32,
+
     pub rocket: Option<Rocket>,
 }
 
@@ -37,20 +44,51 @@ impl Timer {
             t: 0.0,
             now,
             prev_time: now,
+            dt: 0.0,
             frame: 0,
             last_report_time: now,
             last_report_frame: 0,
             accum_draw_time: Duration::default(),
             accum_idle_time: Duration::default(),
             pause: false,
+            rows_per_second: DEFAULT_ROWS_PER_SECOND,
+            row_offset: 0.0,
             rocket: Timer::init_rocket(),
         }
     }
 
+    pub fn rows_per_second(&self) -> f32 {
+        self.rows_per_second
+    }
+
+    /// Change the row rate from now on, keeping the current row.
+    pub fn set_rows_per_second(&mut self, rows_per_second: f32) {
+        self.row_offset += self.t * (self.rows_per_second - rows_per_second);
+        self.rows_per_second = rows_per_second;
+    }
+
+    /// Line up Rocket rows with the rows of an XM module playing at the given tempo.
+    /// To be called again whenever the module changes tempo.
+    pub fn set_tempo(&mut self, bpm: u16, speed: u16) {
+        self.set_rows_per_second(crate::sound::rows_per_second(bpm, speed));
+    }
+
+    /// Current row on the Rocket timeline.
+    pub fn row(&self) -> f32 {
+        self.t * self.rows_per_second + self.row_offset
+    }
+
+    /// Seek to a row on the Rocket timeline.
+    pub fn set_row(&mut self, row: f32) {
+        self.t = (row - self.row_offset) / self.rows_per_second;
+    }
+
     // To be called once per frame, just before rendering
     pub fn update(&mut self) {
         self.prev_time = self.now;
         self.now = Instant::now();
+        // Clamp to avoid huge jumps after stalls, e.g. while dragging the window.
+        self.dt = (self.now - self.prev_time).as_secs_f32().min(0.25);
         if !self.pause {
             // Increment simulation time
             let frame_time = self.now - self.prev_time;
@@ -102,25 +140,34 @@ impl Timer {
     fn poll_rocket(&mut self) {
         use rust_rocket::client::Event;
 
-        match self.rocket {
-            Some(ref mut rocket) => {
-                let current_row = (self.t * BPS) as u32;
-                if let Some(event) = rocket.poll_events().unwrap() {
-                    match event {
-                        Event::SetRow(row) => {
-                            println!("SetRow (row: {:?})", row);
-                            self.t = row as f32 / BPS;
-                        }
-                        Event::Pause(_) => {
-                            let track1 = rocket.get_track_mut("test").unwrap();
-                            println!("Pause (value: {:?}) (row: {:?})", track1.get_value(current_row as f32), current_row);
-                        }
-                        _ => (),
-                    }
-                    println!("{:?}", event);
+        let rocket = match self.rocket {
+            Some(ref mut rocket) => rocket,
+            None => return,
+        };
+        loop {
+            let event = match rocket.poll_events() {
+                Ok(Some(event)) => event,
+                Ok(None) => break,
+                Err(err) => {
+                    // Keep going on the free-running clock.
+                    println!("Lost the connection to the Rocket editor: {:?}", err);
+                    self.rocket = None;
+                    break;
+                }
+            };
+            let current_row = self.row() as u32;
+            match event {
+                Event::SetRow(row) => {
+                    println!("SetRow (row: {:?})", row);
+                    self.set_row(row as f32);
+                }
+                Event::Pause(pause) => {
+                    println!("Pause (pause: {:?}) (row: {:?})", pause, current_row);
+                    self.pause = pause;
                 }
+                _ => (),
             }
-            None => ()
+            println!("{:?}", event);
         }
     }
 }
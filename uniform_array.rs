@@ -0,0 +1,39 @@
+// Arrays of uniforms for glium, set element by element.
+//
+// Uniform blocks would be the natural fit, but glium reads their layout back
+// with glGetActiveUniformName, which GL ES doesn't have: any program using
+// one fails on the default --gl gles:3.0 context. Plain arrays work
+// everywhere, glium sees their elements as name[0], name[1]...
+
+use glium::uniforms::{UniformValue, Uniforms};
+
+/// The values of a `uniform vec4 name[N]` array.
+pub struct Vec4Array<'a> {
+    name: &'a str,
+    values: &'a [[f32; 4]],
+}
+
+impl<'a> Vec4Array<'a> {
+    pub fn new(name: &'a str, values: &'a [[f32; 4]]) -> Vec4Array<'a> {
+        Vec4Array { name, values }
+    }
+}
+
+impl Uniforms for Vec4Array<'_> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut f: F) {
+        for (i, &v) in self.values.iter().enumerate() {
+            f(&format!("{}[{}]", self.name, i), UniformValue::Vec4(v));
+        }
+    }
+}
+
+/// Two sets of uniforms drawn with together, like UniformsStorage::add but
+/// for any of them.
+pub struct Merge<A, B>(pub A, pub B);
+
+impl<A: Uniforms, B: Uniforms> Uniforms for Merge<A, B> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut f: F) {
+        self.0.visit_values(&mut f);
+        self.1.visit_values(&mut f);
+    }
+}
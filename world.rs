@@ -0,0 +1,314 @@
+// The demo scene: the fractal, its bounding box and the sea of cubes.
+
+use crate::bounding_box::{self, BoundingBox};
+use crate::cube::Cube;
+use crate::lights::Lights;
+use crate::mandelwow;
+use crate::options::Options;
+use crate::postprocess::PostProcess;
+use crate::render_target::{self, RenderTarget};
+use crate::retro::{self, RetroFilter};
+use crate::screenshot;
+use crate::sea::Sea;
+use crate::shadow::ShadowMap;
+use crate::support;
+use cgmath::conv::array4x4;
+use cgmath::{Euler, InnerSpace, Matrix4, Rad, SquareMatrix, Vector3, Vector4};
+use glium::backend::Facade;
+use glium::texture::Texture2d;
+use glium::{Display, Program, Rect, Surface, uniform};
+use std::rc::Rc;
+
+// Cheap deterministic scrambling of the user-provided seed.
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Vary the wow factor to slice the Mandelwow along its 4th dimension.
+pub fn wow(t: f32) -> f32 {
+    let wmin = -0.8;
+    let wmax = 0.8;
+    let wsize = wmax - wmin;
+    (((t * 0.7).sin() + 1.0) / 2.0) * wsize + wmin
+}
+
+pub const BLACK: (f32, f32, f32, f32) = (0.0, 0.0, 0.0, 1.0);
+
+// Send the model back a little bit so it fits the screen.
+pub const MODEL_Z: f32 = -3.0;
+
+// Largest distance of the fractal from its center, at twice its size on hits.
+const FRACTAL_RADIUS: f32 = 4.0;
+
+/// Everything in the demo scene.
+pub struct World {
+    mandelwow_program: Rc<Program>,
+    mandelwow_shadow_program: Program,
+    mandelwow_bounds: Cube,
+    mandelwow_bbox: BoundingBox,
+    pub bounding_box_enabled: bool,
+
+    sea: Sea,
+    lights: Lights,
+    // Drawn from the main light each frame, if there is one.
+    shadow: Option<ShadowMap>,
+
+    // Number of slices of the fractal, picked by --quality.
+    pub mandelwow_slices: u32,
+
+    // The scene is rendered offscreen in HDR, then post-processed into the
+    // window. In letterbox mode at 16:9, in the middle of the window.
+    pub letterbox: bool,
+    target: RenderTarget,
+    post: PostProcess,
+    // The retro filter shows the scene at the resolution of the C64, in the
+    // middle of the viewport.
+    pub retro: bool,
+    retro_filter: RetroFilter,
+
+    // For the zoom animation synchronized to the drum-hits
+    pub hit_time: f32,
+    pub last_hit: f32,
+}
+
+impl World {
+    pub fn new<F: ?Sized + Facade>(display: &F, options: &Options) -> World {
+        let (width, height) = display.get_context().get_framebuffer_dimensions();
+        let mandelwow_program = Rc::new(mandelwow::program(display));
+        let bounding_box_program = Rc::new(bounding_box::solid_fill_program(display));
+
+        // These are the bounds for the 3D slice of the 4D Mandelwow
+        let mandelwow_bounds = Cube {
+            xmin: -2.0,
+            xmax: 0.7,
+            ymin: -1.0,
+            ymax: 1.0,
+            zmin: -1.1,
+            zmax: 1.1,
+        };
+
+        // First character shown on the sea, picked by --seed.
+        let glyph_offset = options.seed.map_or(0, |seed| (splitmix64(seed) % 256) as usize);
+
+        // The shadows cover the sea and the fractal, even when it's scaled
+        // up by the hits.
+        let shadow = options.lights.main_light().map(|(_, direction)| {
+            let (sea_min, sea_max) = options.sea.bounds();
+            let fractal = Vector3::unit_z() * MODEL_Z;
+            let min = sea_min.zip(fractal, |a, b| a.min(b - FRACTAL_RADIUS));
+            let max = sea_max.zip(fractal, |a, b| a.max(b + FRACTAL_RADIUS));
+            let mut shadow = ShadowMap::new(display, options.quality.shadow_map_size());
+            shadow.set_light(direction, ((min + max) / 2.0).into(), (max - min).magnitude() / 2.0);
+            shadow
+        });
+
+        World {
+            mandelwow_program,
+            mandelwow_shadow_program: mandelwow::shadow_program(display),
+            mandelwow_bbox: BoundingBox::new(
+                display, &mandelwow_bounds, bounding_box_program),
+            mandelwow_bounds,
+            bounding_box_enabled: true,
+
+            sea: Sea::new(display, &options.sea, glyph_offset),
+            lights: options.lights.clone(),
+            shadow,
+            mandelwow_slices: options.quality.slices(),
+            letterbox: options.letterbox,
+            target: RenderTarget::new_hdr(display, width.max(1), height.max(1)),
+            post: PostProcess::new(display, &options.post),
+            retro: options.retro,
+            retro_filter: RetroFilter::new(display, &options.retro_filter),
+
+            hit_time: 0.0,
+            last_hit: 0.0,
+        }
+    }
+
+    /// Area of the window the scene is drawn into.
+    fn viewport(&self, width: u32, height: u32) -> Rect {
+        let viewport = if self.letterbox {
+            render_target::letterbox(width, height, render_target::WIDESCREEN)
+        } else {
+            Rect { left: 0, bottom: 0, width, height }
+        };
+        if self.retro {
+            retro::screen_rect(&viewport)
+        } else {
+            viewport
+        }
+    }
+
+    /// Size to render the scene at, to fill the viewport.
+    fn scene_size(&self, viewport: &Rect) -> (u32, u32) {
+        if self.retro {
+            (retro::WIDTH, retro::HEIGHT)
+        } else {
+            (viewport.width, viewport.height)
+        }
+    }
+
+    /// Follow the window size: reallocate the offscreen target and fix the aspect ratio.
+    pub fn resize(&mut self, display: &Display, camera: &mut support::camera::CameraState) {
+        let (width, height) = display.get_framebuffer_dimensions();
+        if width == 0 || height == 0 {
+            // Minimized.
+            return;
+        }
+        let viewport = self.viewport(width, height);
+        if viewport.width == 0 || viewport.height == 0 {
+            return;
+        }
+        let (width, height) = self.scene_size(&viewport);
+        self.target.resize(display, width, height);
+        camera.set_aspect(width as f32 / height as f32);
+    }
+
+    /// Draw a whole frame into the window's surface, post-processed, and
+    /// letterboxed and through the retro filter if enabled.
+    pub fn draw_frame<F: ?Sized + Facade, S: Surface>(
+        &self,
+        display: &F,
+        frame: &mut S,
+        camera: &support::camera::CameraState,
+        t: f32,
+    ) {
+        let (width, height) = frame.get_dimensions();
+        self.draw_scene(display, &mut self.target.framebuffer(display), camera, t, BLACK);
+        if self.letterbox || self.retro {
+            frame.clear_color_and_depth(BLACK, 1.0);
+        }
+        self.post_process(display, self.target.color(), frame, &self.viewport(width, height), t);
+    }
+
+    // Bring the HDR scene to the viewport of the frame.
+    fn post_process<F: ?Sized + Facade, S: Surface>(
+        &self,
+        display: &F,
+        scene: &Texture2d,
+        frame: &mut S,
+        viewport: &Rect,
+        t: f32,
+    ) {
+        if self.retro {
+            let screen = Rect { left: 0, bottom: 0, width: retro::WIDTH, height: retro::HEIGHT };
+            self.post.apply(display, scene, &mut self.retro_filter.framebuffer(display), &screen, t);
+            self.retro_filter.draw(frame, viewport);
+        } else {
+            self.post.apply(display, scene, frame, viewport, t);
+        }
+    }
+
+    /// Render the current view offscreen, possibly larger than the window, and save it.
+    pub fn screenshot(
+        &self,
+        display: &Display,
+        camera: &support::camera::CameraState,
+        t: f32,
+        settings: &screenshot::ScreenshotSettings,
+    ) -> Result<std::path::PathBuf, String> {
+        let (window_width, window_height) = display.get_framebuffer_dimensions();
+        let viewport = self.viewport(window_width, window_height);
+        let (width, height) = settings.render_size(viewport.width, viewport.height);
+        let cant_render = |err| format!("can't render at {}x{}: {}", width, height, err);
+        let (scene_width, scene_height) = if self.retro { self.scene_size(&viewport) } else { (width, height) };
+        let scene = RenderTarget::try_new_hdr(display, scene_width, scene_height).map_err(cant_render)?;
+        let target = RenderTarget::try_new(display, width, height).map_err(cant_render)?;
+        let background = if settings.alpha { (0.0, 0.0, 0.0, 0.0) } else { BLACK };
+        self.draw_scene(display, &mut scene.framebuffer(display), camera, t, background);
+        let rect = Rect { left: 0, bottom: 0, width, height };
+        self.post_process(display, scene.color(), &mut target.framebuffer(display), &rect, t);
+
+        let pixels = screenshot::downsample(&target.read_pixels(), width, height, settings.supersample);
+        let (width, height) = (width / settings.supersample, height / settings.supersample);
+        let info = screenshot::SceneInfo {
+            t,
+            hit_time: self.hit_time,
+            wow: wow(t),
+            pos: camera.get_pos(),
+            orientation: camera.get_orientation(),
+            fov: camera.get_fov(),
+            formula: mandelwow::FORMULA.to_string(),
+            max_iter: mandelwow::MAX_ITER,
+            slices: self.mandelwow_slices,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        };
+        screenshot::take_screenshot(&pixels, width, height, settings, &info)
+            .map_err(|err| err.to_string())
+    }
+
+    pub fn draw_scene<F: ?Sized + Facade, S: Surface>(
+        &self,
+        display: &F,
+        frame: &mut S,
+        camera: &support::camera::CameraState,
+        t: f32,
+        background: (f32, f32, f32, f32),
+    ) {
+        let perspview = camera.get_perspview();
+
+        let hit_delta = t - self.hit_time;
+        let hit_scale = 1. / (1. + hit_delta * hit_delta * 15.0) + 1.;
+
+        let wow = wow(t);
+
+        //println!("t={} w={:?} camera={:?}", t, w, camera.get_pos());
+
+        frame.clear_color_and_depth(background, 1.0);
+
+        let rotation = Matrix4::from(Euler {
+            x: Rad(t.sin() / 3.),
+            y: Rad(t.sin() / 2.),
+            z: Rad(t / 1.5),
+        });
+        let scale = Matrix4::from_diagonal(Vector4::new(hit_scale, hit_scale, hit_scale, 1.0));
+        let model2 = Matrix4::from_translation(Vector3::unit_z() * MODEL_Z) * rotation * scale;
+        let model = array4x4(model2);
+
+        // Draw the bounding box before the fractal, when the Z-buffer is still clear,
+        // so the lines behind the semi-translucent areas will be drawn.
+        if self.bounding_box_enabled {
+            let uniforms = uniform! {
+                model: model,
+                view:  camera.get_view(),
+                perspective: camera.get_perspective(),
+            };
+            self.mandelwow_bbox.draw(frame, &uniforms);
+        }
+
+        if let Some(shadow) = &self.shadow {
+            let mut shadow_frame = shadow.framebuffer(display);
+            self.sea.draw_shadow(&mut shadow_frame, shadow, t);
+            mandelwow::draw(
+                display,
+                &mut shadow_frame,
+                &self.mandelwow_shadow_program,
+                model,
+                shadow.view(),
+                shadow.projection(),
+                &self.mandelwow_bounds,
+                wow,
+                self.mandelwow_slices,
+            );
+        }
+
+        let eye = camera.get_pos();
+        self.sea.draw(frame, &perspview, [eye.0, eye.1, eye.2], t, &self.lights, self.shadow.as_ref());
+
+        mandelwow::draw(
+            display,
+            frame,
+            &self.mandelwow_program,
+            model,
+            camera.get_view(),
+            camera.get_perspective(),
+            &self.mandelwow_bounds,
+            wow,
+            self.mandelwow_slices,
+        );
+    }
+}
+